@@ -0,0 +1,163 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A precondition of a command or step, used by `if` and `unless`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(untagged)]
+pub enum Condition {
+    /// A shell test run with the entrypoint of the command, true when it exits successfully,
+    /// such as `test -f Cargo.lock` or `git diff --quiet`.
+    Shell(String),
+    /// Checks of the environment hoi runs in, true when all of them hold.
+    Check(ConditionCheck),
+}
+
+/// Checks of the environment hoi runs in.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionCheck {
+    /// An environment variable that must be set to a non-empty value, such as `CI`.
+    #[serde(default)]
+    pub env: Option<String>,
+
+    /// A file or directory that must exist, relative to the configuration file.
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+
+    /// The operating system hoi must run on, such as `linux`, `macos` or `windows`, or
+    /// the family of operating systems, `unix` or `windows`.
+    #[serde(default)]
+    pub platform: Option<String>,
+}
+
+impl Condition {
+    /// Returns a short description of the condition for messages, as in `env CI`.
+    pub fn describe(&self) -> String {
+        match self {
+            Condition::Shell(test) => format!("`{}`", test.trim()),
+            Condition::Check(check) => {
+                let mut parts = Vec::new();
+                if let Some(name) = &check.env {
+                    parts.push(format!("env {}", name));
+                }
+                if let Some(file) = &check.file {
+                    parts.push(format!("file {}", file.display()));
+                }
+                if let Some(platform) = &check.platform {
+                    parts.push(format!("platform {}", platform));
+                }
+                parts.join(", ")
+            }
+        }
+    }
+
+    /// Evaluates the condition.
+    ///
+    /// # Arguments
+    /// * `base_dir` - The directory relative file paths are resolved against
+    /// * `lookup` - Returns the value of an environment variable, or None if it is not set
+    /// * `shell` - Runs a shell test and returns whether it exited successfully
+    ///
+    /// # Returns
+    /// * `io::Result<bool>` - Whether the condition holds, or an error if a shell test could
+    ///   not be started
+    pub fn holds(
+        &self,
+        base_dir: Option<&Path>,
+        lookup: impl Fn(&str) -> Option<String>,
+        shell: impl FnOnce(&str) -> io::Result<bool>,
+    ) -> io::Result<bool> {
+        let check = match self {
+            Condition::Shell(test) => return shell(test),
+            Condition::Check(check) => check,
+        };
+
+        if let Some(name) = &check.env {
+            if lookup(name).is_none_or(|value| value.is_empty()) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(file) = &check.file {
+            let path = match base_dir {
+                Some(base_dir) => base_dir.join(file),
+                None => file.clone(),
+            };
+            if !path.exists() {
+                return Ok(false);
+            }
+        }
+
+        if let Some(platform) = &check.platform {
+            if platform != env::consts::OS && platform != env::consts::FAMILY {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use testdir::testdir;
+
+    #[test]
+    fn test_condition_holds() {
+        let temp_dir = testdir!();
+        fs::write(temp_dir.join("Cargo.lock"), "").unwrap();
+
+        let conditions: Vec<Condition> = serde_yaml::from_str(&format!(
+            r#"
+- test -f Cargo.lock
+- env: CI
+- env: EMPTY
+- file: Cargo.lock
+- file: missing.txt
+- platform: {}
+- platform: {}
+  env: CI
+- platform: plan9
+"#,
+            env::consts::OS,
+            env::consts::FAMILY
+        ))
+        .unwrap();
+
+        let lookup = |name: &str| match name {
+            "CI" => Some("true".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        let holds: Vec<bool> = conditions
+            .iter()
+            .map(|condition| {
+                condition
+                    .holds(Some(&temp_dir), lookup, |test| {
+                        Ok(test == "test -f Cargo.lock")
+                    })
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(
+            holds,
+            vec![true, true, false, true, false, true, true, false]
+        );
+        assert_eq!(conditions[0].describe(), "`test -f Cargo.lock`");
+        assert_eq!(
+            conditions[6].describe(),
+            format!("env CI, platform {}", env::consts::FAMILY)
+        );
+    }
+
+    #[test]
+    fn test_condition_rejects_unknown_checks() {
+        assert!(serde_yaml::from_str::<Condition>("os: linux").is_err());
+    }
+}
@@ -0,0 +1,732 @@
+use crate::environment::{load_environment_files, Dotenv, LoadedEnvironment};
+use crate::format::{find_config_in_dir, ConfigFormat};
+use crate::hoi::{ColorChoice, CommandSort, Hoi, HoiError};
+use crate::logs::LogSettings;
+use crate::overrides::LocalOverrides;
+use crate::user_command::UserCommand;
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// The merged result of the global and local Hoi configuration files.
+#[derive(Debug, Default)]
+pub struct Config {
+    /// The merged configuration, with local settings and commands taking precedence.
+    pub hoi: Hoi,
+
+    /// The paths to the local (project-specific) configuration files that were found,
+    /// ordered from the outermost directory to the innermost one.
+    pub local_paths: Vec<PathBuf>,
+
+    /// The path to the global configuration file, if one was found.
+    pub global_path: Option<PathBuf>,
+
+    /// The dotenv setting of the global configuration file, used for global commands.
+    global_dotenv: Option<Dotenv>,
+
+    /// The names of the commands defined in the global configuration file.
+    global_commands: Vec<String>,
+}
+
+impl Config {
+    /// Discovers and merges the Hoi configuration files visible from a directory.
+    ///
+    /// The global config at ~/.hoi/.hoi.global.yml is loaded first, then every .hoi.yml
+    /// found in `dir` and its parents, from the outermost directory to the innermost
+    /// one. The walk up stops early at a .hoi.yml that sets `root: true`. Commands from
+    /// later files override commands with the same name, and an entrypoint or
+    /// description from a later file overrides the earlier one. A .hoi.local.yml file
+    /// next to a .hoi.yml file is applied right after it.
+    ///
+    /// Each of these files may also be written as TOML or JSON, using the `.toml` or
+    /// `.json` extension instead of `.yml`.
+    ///
+    /// # Arguments
+    /// * `dir` - The directory to start searching for configuration files from
+    ///
+    /// # Returns
+    /// * `Result<Config, HoiError>` - The merged configuration
+    ///
+    /// # Errors
+    /// * `HoiError::UnreadableConfig` - If a configuration file cannot be read
+    /// * `HoiError::InvalidConfig` - If a configuration file is invalid
+    /// * `HoiError::NoCommandsDefined` - If a local configuration file doesn't define any commands
+    /// * `HoiError::InvalidOverride` - If a .hoi.local.yml file adds a command without a `cmd`
+    pub fn discover(dir: &Path) -> Result<Config, HoiError> {
+        let mut config = Config {
+            global_path: find_global_config_file(),
+            ..Config::default()
+        };
+
+        // Load and merge global config if it exists. It may only set an entrypoint.
+        if let Some(global_path) = config.global_path.clone() {
+            let mut global_hoi = parse_config(&global_path)?;
+            config.global_dotenv = global_hoi.dotenv.take();
+            config.global_commands = global_hoi.commands.keys().cloned().collect();
+            config.merge(global_hoi, &global_path);
+        }
+
+        // Collect local configs from the innermost directory up, stopping at a root config
+        let mut local_configs = Vec::new();
+        for path in find_config_files(dir) {
+            let hoi = load_config(&path)?;
+            let is_root = hoi.root;
+            local_configs.push((path, hoi));
+
+            if is_root {
+                break;
+            }
+        }
+
+        // Merge local configs from the outermost directory in (overriding global settings)
+        for (path, local_hoi) in local_configs.into_iter().rev() {
+            config.merge(local_hoi, &path);
+
+            // Apply personal overrides from a .hoi.local.yml next to the .hoi.yml
+            if let Some(overrides_path) = path
+                .parent()
+                .and_then(|dir| find_config_in_dir(dir, ".hoi.local"))
+            {
+                load_overrides(&overrides_path)?.apply(&mut config.hoi, &overrides_path)?;
+            }
+
+            config.local_paths.push(path);
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the names of the variables whose values are masked in a command's output.
+    ///
+    /// These are the configured `secret` names and, when `secret_env_local` is set, every
+    /// variable defined in a .env.local file the command loads.
+    ///
+    /// # Arguments
+    /// * `command` - The command that is about to run
+    pub fn secret_names(&self, command: &UserCommand) -> Vec<String> {
+        let mut names = self.hoi.secret.clone();
+
+        if self.hoi.secret_env_local {
+            let env_local_files = self
+                .environment_files(command)
+                .into_iter()
+                .filter(|file| file.file_name().is_some_and(|name| name == ".env.local"));
+
+            for file in env_local_files {
+                if let Ok(vars) = dotenvy::from_path_iter(&file) {
+                    names.extend(vars.filter_map(Result::ok).map(|(name, _)| name));
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Activates a profile, applying its overrides on top of the merged configuration.
+    ///
+    /// See [`Hoi::apply_profile`] for details.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the profile to activate
+    ///
+    /// # Errors
+    /// * `HoiError::ProfileNotFound` - If profiles are defined but none has this name
+    /// * `HoiError::InvalidOverride` - If the profile adds a command without a `cmd`
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), HoiError> {
+        let path = self
+            .local_path()
+            .or(self.global_path.as_deref())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        self.hoi.apply_profile(name, &path)
+    }
+
+    /// Returns true if a command from a local file replaces a global command with the same name.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the command
+    pub fn shadows_global(&self, name: &str) -> bool {
+        self.global_commands.iter().any(|global| global == name)
+            && self
+                .hoi
+                .commands
+                .get(name)
+                .is_some_and(|command| command.source != self.global_path)
+    }
+
+    /// Returns the path to the innermost local configuration file, if one was found.
+    pub fn local_path(&self) -> Option<&Path> {
+        self.local_paths.last().map(PathBuf::as_path)
+    }
+
+    /// Returns the directory command logs are written to: `.hoi/logs` next to the innermost
+    /// configuration file, or ~/.hoi/logs when only the global configuration was found.
+    pub fn log_dir(&self) -> Option<PathBuf> {
+        let config_dir = match self.local_path() {
+            Some(path) => path.parent()?.to_path_buf(),
+            None => dirs_next::home_dir()?,
+        };
+
+        Some(config_dir.join(".hoi").join("logs"))
+    }
+
+    /// Returns the dotenv files to load before running a command.
+    ///
+    /// Commands from the global configuration use the files listed in its `dotenv` setting,
+    /// relative to ~/.hoi. All other commands use the `dotenv` setting of the local
+    /// configuration, relative to the innermost configuration file. Both default to
+    /// .env followed by .env.local.
+    ///
+    /// # Arguments
+    /// * `command` - The command that is about to run
+    pub fn environment_files(&self, command: &UserCommand) -> Vec<PathBuf> {
+        let is_global = command.source.is_some() && command.source == self.global_path;
+        let (config_path, dotenv) = if is_global {
+            (self.global_path.as_deref(), &self.global_dotenv)
+        } else {
+            (self.local_path(), &self.hoi.dotenv)
+        };
+
+        match config_path.and_then(Path::parent) {
+            Some(config_dir) => dotenv.clone().unwrap_or_default().files(config_dir),
+            None => Vec::new(),
+        }
+    }
+
+    /// Loads the dotenv files for a command into the process environment.
+    ///
+    /// See [`Config::environment_files`] for which files are loaded, and
+    /// [`load_environment_files`] for the precedence rules.
+    ///
+    /// # Arguments
+    /// * `command` - The command that is about to run
+    pub fn load_environment(&self, command: &UserCommand) -> LoadedEnvironment {
+        load_environment_files(&self.environment_files(command))
+    }
+
+    /// Merges a configuration loaded from `path` on top of the current one.
+    fn merge(&mut self, hoi: Hoi, path: &Path) {
+        // Override entrypoint if defined
+        if !hoi.entrypoint.is_empty() {
+            self.hoi.entrypoint = hoi.entrypoint;
+        }
+
+        // Override description if defined
+        if !hoi.description.is_empty() {
+            self.hoi.description = hoi.description;
+        }
+
+        // Override dotenv files if defined
+        if hoi.dotenv.is_some() {
+            self.hoi.dotenv = hoi.dotenv;
+        }
+
+        // Override the command order if defined
+        if hoi.sort != CommandSort::default() {
+            self.hoi.sort = hoi.sort;
+        }
+
+        // Override the listing settings if defined
+        if hoi.banner.is_some() {
+            self.hoi.banner = hoi.banner;
+        }
+        if !hoi.tips.is_empty() {
+            self.hoi.tips = hoi.tips;
+        }
+        if hoi.color != ColorChoice::default() {
+            self.hoi.color = hoi.color;
+        }
+
+        // Override log settings if defined
+        if hoi.logs != LogSettings::default() {
+            self.hoi.logs = hoi.logs;
+        }
+
+        // Require the tools of every file
+        for requirement in hoi.requires {
+            if !self.hoi.requires.contains(&requirement) {
+                self.hoi.requires.push(requirement);
+            }
+        }
+
+        // Require the environment variables of every file
+        for required in hoi.env_required {
+            if !self.hoi.env_required.contains(&required) {
+                self.hoi.env_required.push(required);
+            }
+        }
+
+        // Mark secrets from every file
+        self.hoi.secret.extend(hoi.secret);
+        self.hoi.secret_env_local |= hoi.secret_env_local;
+
+        // Add variables, environment variables and profiles (overriding earlier ones)
+        self.hoi.vars.extend(hoi.vars);
+        self.hoi.env.extend(hoi.env);
+        self.hoi.profiles.extend(hoi.profiles);
+
+        // Add commands (overriding any previously defined commands with the same name)
+        for (name, mut command) in hoi.commands {
+            command.source = Some(path.to_path_buf());
+            self.hoi.commands.insert(name, command);
+        }
+    }
+}
+
+/// Searches for a .hoi.yml configuration file in the given directory and its parents.
+/// Returns the path to the first .hoi.yml file found, or None if no configuration file exists.
+///
+/// # Arguments
+/// * `dir` - The directory to start searching from
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    find_config_files(dir).into_iter().next()
+}
+
+/// Searches for .hoi.yml (or .hoi.toml / .hoi.json) configuration files in the given directory
+/// and all of its parents. Returns the paths ordered from the innermost directory to the outermost one.
+///
+/// # Arguments
+/// * `dir` - The directory to start searching from
+pub fn find_config_files(dir: &Path) -> Vec<PathBuf> {
+    let mut config_paths = Vec::new();
+
+    for dir in dir.ancestors() {
+        if let Some(config_path) = find_config_in_dir(dir, ".hoi") {
+            // On Windows, avoid canonicalize() as it can lead to path format issues
+            #[cfg(not(windows))]
+            config_paths.push(config_path.canonicalize().unwrap_or(config_path));
+
+            // For Windows, just use the path directly
+            #[cfg(windows)]
+            config_paths.push(config_path);
+        }
+    }
+
+    config_paths
+}
+
+/// Checks for a global .hoi.global.yml (or .hoi.global.toml / .hoi.global.json) configuration
+/// file in the user's home directory.
+/// Returns the path to the global config file if it exists, or None if it doesn't.
+pub fn find_global_config_file() -> Option<PathBuf> {
+    if let Some(home_dir) = dirs_next::home_dir() {
+        // Look for the global config in any supported format
+        if let Some(global_config) = find_config_in_dir(&home_dir.join(".hoi"), ".hoi.global") {
+            return match global_config.canonicalize() {
+                Ok(path) => Some(path),
+                Err(_) => Some(global_config),
+            };
+        }
+    }
+
+    None
+}
+
+/// Loads and parses the Hoi configuration file from the specified path.
+/// The file format (YAML, TOML or JSON) is chosen from the file extension.
+///
+/// # Arguments
+/// * `path` - The path to the configuration file
+///
+/// # Returns
+/// * `Result<Hoi, HoiError>` - The parsed Hoi configuration struct or an error
+///
+/// # Errors
+/// * `HoiError::UnreadableConfig` - If the file cannot be read
+/// * `HoiError::InvalidConfig` - If the file is invalid
+/// * `HoiError::NoCommandsDefined` - If the configuration doesn't define any commands
+pub fn load_config(path: &Path) -> Result<Hoi, HoiError> {
+    let hoi = parse_config(path)?;
+
+    if hoi.commands.is_empty() {
+        return Err(HoiError::NoCommandsDefined(path.to_path_buf()));
+    }
+
+    Ok(hoi)
+}
+
+/// Loads and parses the Hoi configuration file from the specified path, without
+/// requiring it to define any commands.
+///
+/// # Arguments
+/// * `path` - The path to the configuration file
+///
+/// # Errors
+/// * `HoiError::UnreadableConfig` - If the file cannot be read
+/// * `HoiError::InvalidConfig` - If the file is invalid
+pub fn parse_config(path: &Path) -> Result<Hoi, HoiError> {
+    ConfigFormat::from_path(path).parse_file(path, &read_config_file(path)?)
+}
+
+/// Loads and parses a .hoi.local.yml overrides file from the specified path.
+/// The file format (YAML, TOML or JSON) is chosen from the file extension.
+///
+/// # Arguments
+/// * `path` - The path to the overrides file
+///
+/// # Errors
+/// * `HoiError::UnreadableConfig` - If the file cannot be read
+/// * `HoiError::InvalidConfig` - If the file is invalid
+pub fn load_overrides(path: &Path) -> Result<LocalOverrides, HoiError> {
+    ConfigFormat::from_path(path).parse_file(path, &read_config_file(path)?)
+}
+
+/// Reads the contents of a configuration file.
+fn read_config_file(path: &Path) -> Result<String, HoiError> {
+    let unreadable = |source| HoiError::UnreadableConfig {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let file = fs::File::open(path).map_err(unreadable)?;
+    let mut buf_reader = BufReader::new(file);
+    let mut contents = String::new();
+    buf_reader
+        .read_to_string(&mut contents)
+        .map_err(unreadable)?;
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self};
+    use std::path::PathBuf;
+    use temp_env::with_var;
+    use testdir::testdir;
+    use utilities::copy_fixture;
+
+    #[test]
+    fn test_custom_entrypoint() {
+        let temp_dir: PathBuf = testdir!();
+        copy_fixture(".hoi.with_entrypoint.yml", &temp_dir, ".hoi.yml");
+        let result = load_config(&temp_dir.join(".hoi.yml"));
+
+        assert!(
+            result.is_ok(),
+            "Failed to load valid config: {:?}",
+            result.err()
+        );
+
+        let hoi = result.unwrap();
+        assert_eq!(hoi.entrypoint, vec!["sh", "-c", "$@"]);
+    }
+
+    #[test]
+    fn test_find_config() {
+        let temp_dir: PathBuf = testdir!();
+        copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+        let config_path = temp_dir.join(".hoi.yml");
+        let nested_dir = temp_dir.join("nested").join("dir");
+        fs::create_dir_all(&nested_dir).unwrap();
+
+        let result = find_config_file(&nested_dir);
+        assert!(result.is_some(), "Failed to find config file");
+
+        #[cfg(not(windows))]
+        {
+            let canonical_path = config_path.canonicalize().ok().unwrap();
+            assert_eq!(result.unwrap(), canonical_path);
+        }
+
+        #[cfg(windows)]
+        {
+            let result_path = result.unwrap();
+            // For Windows, just check that the file exists and has the right name
+            assert!(result_path.exists(), "Result path does not exist");
+            assert_eq!(
+                result_path.file_name().unwrap(),
+                config_path.file_name().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_global_config() {
+        let temp_dir: PathBuf = testdir!();
+
+        #[cfg(not(windows))]
+        let (env_var, env_val) = ("HOME", &temp_dir);
+        #[cfg(windows)]
+        let (env_var, env_val) = ("USERPROFILE", &temp_dir);
+
+        with_var(env_var, Some(env_val.to_str().unwrap()), || {
+            let home_dir = dirs_next::home_dir().unwrap();
+            let hoi_dir = home_dir.join(".hoi");
+            fs::create_dir_all(&hoi_dir).unwrap();
+            copy_fixture(".hoi.global.yml", &hoi_dir, ".hoi.global.yml");
+
+            let result = find_global_config_file();
+            assert!(result.is_some(), "Failed to find global config file");
+
+            let result_path = result.unwrap();
+
+            // Platform-specific path comparison
+            #[cfg(not(windows))]
+            {
+                let canonical_path = hoi_dir.join(".hoi.global.yml").canonicalize().unwrap();
+                assert_eq!(result_path, canonical_path);
+            }
+
+            #[cfg(windows)]
+            {
+                // For Windows, just check that the file exists and has the right name
+                assert!(result_path.exists(), "Result path does not exist");
+                assert_eq!(
+                    result_path.file_name().unwrap(),
+                    hoi_dir.join(".hoi.global.yml").file_name().unwrap()
+                );
+
+                // Also verify the parent directory is correct
+                assert_eq!(
+                    result_path.parent().unwrap().file_name().unwrap(),
+                    hoi_dir
+                        .join(".hoi.global.yml")
+                        .parent()
+                        .unwrap()
+                        .file_name()
+                        .unwrap()
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_discover_merges_global_and_local() {
+        let temp_dir: PathBuf = testdir!();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(temp_dir.to_str().unwrap()), || {
+            let hoi_dir = temp_dir.join(".hoi");
+            fs::create_dir_all(&hoi_dir).unwrap();
+            copy_fixture(".hoi.global.yml", &hoi_dir, ".hoi.global.yml");
+            copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+
+            let config = Config::discover(&temp_dir).unwrap();
+            assert!(config.local_path().is_some());
+            assert!(config.global_path.is_some());
+            assert_eq!(config.hoi.description, "Integration test config");
+            assert!(config.hoi.commands.contains_key("echo-test"));
+            assert!(config.hoi.commands.contains_key("global-echo"));
+            assert_eq!(
+                config.hoi.find_command("ge").map(|(name, _)| name),
+                Some("global-echo")
+            );
+        });
+    }
+
+    #[test]
+    fn test_discover_layers_parent_configs() {
+        let temp_dir: PathBuf = testdir!();
+        let service_dir = temp_dir.join("services").join("api");
+        fs::create_dir_all(&service_dir).unwrap();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(temp_dir.to_str().unwrap()), || {
+            fs::write(
+                temp_dir.join(".hoi.yml"),
+                "commands:\n  build:\n    cmd: echo root\n  lint:\n    cmd: echo lint\n",
+            )
+            .unwrap();
+            fs::write(
+                service_dir.join(".hoi.yml"),
+                "commands:\n  build:\n    cmd: echo api\n",
+            )
+            .unwrap();
+
+            let config = Config::discover(&service_dir).unwrap();
+            assert_eq!(config.local_paths.len(), 2);
+            assert_eq!(config.hoi.commands["build"].cmd, "echo api");
+            assert_eq!(config.hoi.commands["lint"].cmd, "echo lint");
+            assert_eq!(
+                config.hoi.commands["lint"].dir().unwrap().file_name(),
+                temp_dir.file_name()
+            );
+            assert_eq!(
+                config.hoi.commands["build"].dir().unwrap().file_name(),
+                service_dir.file_name()
+            );
+
+            // A root config stops the walk up
+            fs::write(
+                service_dir.join(".hoi.yml"),
+                "root: true\ncommands:\n  build:\n    cmd: echo api\n",
+            )
+            .unwrap();
+
+            let config = Config::discover(&service_dir).unwrap();
+            assert_eq!(config.local_paths.len(), 1);
+            assert!(!config.hoi.commands.contains_key("lint"));
+        });
+    }
+
+    #[test]
+    fn test_discover_applies_local_overrides() {
+        let temp_dir: PathBuf = testdir!();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(temp_dir.to_str().unwrap()), || {
+            copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+            copy_fixture(".hoi.local.yml", &temp_dir, ".hoi.local.yml");
+
+            let config = Config::discover(&temp_dir).unwrap();
+            let commands = &config.hoi.commands;
+
+            assert!(!commands.contains_key("echo-env"));
+            assert_eq!(commands["echo-test"].cmd, "echo \"Local override\"");
+            assert_eq!(commands["echo-test"].env["LOCAL_ONLY"], "1");
+            assert_eq!(
+                commands["echo-test"].description,
+                "Prints a test success message"
+            );
+            assert_eq!(commands["mine"].cmd, "echo \"Just for me\"");
+            assert_eq!(
+                commands["mine"].source.as_ref().unwrap().file_name(),
+                Some(std::ffi::OsStr::new(".hoi.local.yml"))
+            );
+        });
+    }
+
+    #[test]
+    fn test_load_overrides_rejects_unknown_keys() {
+        let temp_dir: PathBuf = testdir!();
+        let path = temp_dir.join(".hoi.local.yml");
+
+        // A misspelled key would otherwise be ignored without a word
+        fs::write(&path, "commands:\n  build:\n    disable: true\n").unwrap();
+        match load_overrides(&path) {
+            Err(HoiError::InvalidConfig { message, .. }) => {
+                assert!(message.contains("disable"), "{}", message)
+            }
+            other => panic!("expected an invalid config, got {:?}", other),
+        }
+
+        fs::write(&path, "comands:\n  build:\n    disabled: true\n").unwrap();
+        assert!(matches!(
+            load_overrides(&path),
+            Err(HoiError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn test_broken_configs_are_reported() {
+        let temp_dir: PathBuf = testdir!();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(temp_dir.to_str().unwrap()), || {
+            // A global config with only an entrypoint is fine
+            let hoi_dir = temp_dir.join(".hoi");
+            fs::create_dir_all(&hoi_dir).unwrap();
+            fs::write(
+                hoi_dir.join(".hoi.global.yml"),
+                "entrypoint: [sh, -c, \"$@\"]\n",
+            )
+            .unwrap();
+            copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+            let config = Config::discover(&temp_dir).unwrap();
+            assert_eq!(config.hoi.entrypoint, vec!["sh", "-c", "$@"]);
+
+            // A local config without commands names the file
+            fs::write(temp_dir.join(".hoi.yml"), "version: 1\n").unwrap();
+            let error = Config::discover(&temp_dir).unwrap_err();
+            assert!(
+                matches!(error, HoiError::NoCommandsDefined(ref path) if path.ends_with(".hoi.yml"))
+            );
+
+            // A broken config names the file and points at the problem
+            fs::write(
+                temp_dir.join(".hoi.yml"),
+                "commands:\n  build:\n    cmd: [echo\n",
+            )
+            .unwrap();
+            let message = Config::discover(&temp_dir).unwrap_err().to_string();
+            assert!(message.contains(".hoi.yml"), "{}", message);
+            assert!(message.contains(" | "), "{}", message);
+            assert!(message.contains('^'), "{}", message);
+        });
+    }
+
+    #[test]
+    fn test_environment_files_for_global_and_local_commands() {
+        let temp_dir: PathBuf = testdir!();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(temp_dir.to_str().unwrap()), || {
+            let hoi_dir = temp_dir.join(".hoi");
+            fs::create_dir_all(&hoi_dir).unwrap();
+            copy_fixture(".hoi.global.yml", &hoi_dir, ".hoi.global.yml");
+            fs::write(
+                temp_dir.join(".hoi.yml"),
+                "dotenv: [.env.shared]\ncommands:\n  build:\n    cmd: echo build\n",
+            )
+            .unwrap();
+
+            let config = Config::discover(&temp_dir).unwrap();
+            let global_dir = config.global_path.as_ref().unwrap().parent().unwrap();
+            let local_dir = config.local_path().unwrap().parent().unwrap();
+
+            assert_eq!(
+                config.environment_files(&config.hoi.commands["global-echo"]),
+                vec![global_dir.join(".env"), global_dir.join(".env.local")]
+            );
+            assert_eq!(
+                config.environment_files(&config.hoi.commands["build"]),
+                vec![local_dir.join(".env.shared")]
+            );
+        });
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let temp_dir: PathBuf = testdir!();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(temp_dir.to_str().unwrap()), || {
+            copy_fixture(".hoi.profiles.yml", &temp_dir, ".hoi.yml");
+
+            let mut config = Config::discover(&temp_dir).unwrap();
+            assert_eq!(config.hoi.vars["cluster"], "dev-cluster");
+
+            config.apply_profile("staging").unwrap();
+            assert_eq!(config.hoi.active_profile.as_deref(), Some("staging"));
+            assert_eq!(config.hoi.vars["cluster"], "staging-cluster");
+            assert_eq!(config.hoi.env["LOG_LEVEL"], "info");
+            assert_eq!(config.hoi.env["APP_NAME"], "hoi");
+            assert_eq!(
+                config.hoi.commands["deploy"].cmd,
+                "echo \"Deploying {{cluster}} with approval\""
+            );
+
+            let mut config = Config::discover(&temp_dir).unwrap();
+            let error = config.apply_profile("qa").unwrap_err();
+            assert!(matches!(error, HoiError::ProfileNotFound(..)));
+            assert_eq!(config.hoi.active_profile, None);
+        });
+    }
+}
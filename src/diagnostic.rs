@@ -0,0 +1,136 @@
+use crate::hoi::HoiError;
+use std::path::Path;
+
+/// Turns an error from parsing a configuration file into an error that names the file
+/// and, when the parser reported a location, shows the offending line.
+///
+/// # Arguments
+/// * `path` - The path of the file that failed to parse
+/// * `contents` - The contents of the file
+/// * `error` - The error returned by the parser
+pub fn invalid_config(path: &Path, contents: &str, error: HoiError) -> HoiError {
+    let message = match &error {
+        // The TOML error display already contains its own snippet
+        HoiError::TomlParsing(e) => e.message().to_string(),
+        HoiError::YamlParsing(e) => e.to_string(),
+        HoiError::JsonParsing(e) => e.to_string(),
+        _ => return error,
+    };
+
+    let message = match error_location(&error, contents) {
+        Some(location) => format!("{}\n{}", message, render_snippet(path, contents, location)),
+        None => message,
+    };
+
+    HoiError::InvalidConfig {
+        path: path.to_path_buf(),
+        message,
+    }
+}
+
+/// A position in a configuration file, with 1-based line and column numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Converts a byte offset into the contents of a file into a line and column.
+    ///
+    /// # Arguments
+    /// * `contents` - The contents of the file
+    /// * `offset` - The byte offset into `contents`
+    pub fn from_offset(contents: &str, offset: usize) -> Location {
+        let before = &contents[..offset.min(contents.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        Location {
+            line,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// Returns where in the file a parsing error occurred, if the parser reported it.
+///
+/// # Arguments
+/// * `error` - The error returned while parsing the file
+/// * `contents` - The contents of the file that failed to parse
+pub fn error_location(error: &HoiError, contents: &str) -> Option<Location> {
+    match error {
+        HoiError::YamlParsing(e) => e.location().map(|l| Location {
+            line: l.line(),
+            column: l.column(),
+        }),
+        HoiError::TomlParsing(e) => e
+            .span()
+            .map(|span| Location::from_offset(contents, span.start)),
+        HoiError::JsonParsing(e) if e.line() > 0 => Some(Location {
+            line: e.line(),
+            column: e.column().max(1),
+        }),
+        _ => None,
+    }
+}
+
+/// Renders the line of a file a location points at, with a caret under the column.
+///
+/// ```text
+///  --> .hoi.yml:3:10
+///   |
+/// 3 |     cmd: [echo
+///   |          ^
+/// ```
+///
+/// # Arguments
+/// * `path` - The path of the file, shown in the header
+/// * `contents` - The contents of the file
+/// * `location` - The location to point at
+pub fn render_snippet(path: &Path, contents: &str, location: Location) -> String {
+    let line_number = location.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+    let line = contents
+        .lines()
+        .nth(location.line.saturating_sub(1))
+        .unwrap_or("");
+
+    // Keep tabs so the caret lines up with the source line
+    let padding: String = line
+        .chars()
+        .take(location.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    format!(
+        "{gutter}--> {}:{}:{}\n{gutter} |\n{line_number} | {line}\n{gutter} | {padding}^",
+        path.display(),
+        location.line,
+        location.column,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_snippet() {
+        let contents = "version: 1\ncommands:\n  build:\n    cmd: [echo\n";
+        let location = Location::from_offset(contents, contents.find("[echo").unwrap());
+        assert_eq!(
+            location,
+            Location {
+                line: 4,
+                column: 10
+            }
+        );
+
+        let snippet = render_snippet(Path::new(".hoi.yml"), contents, location);
+        assert_eq!(
+            snippet,
+            " --> .hoi.yml:4:10\n  |\n4 |     cmd: [echo\n  |          ^"
+        );
+    }
+}
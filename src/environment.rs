@@ -0,0 +1,264 @@
+use indexmap::IndexMap;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Which dotenv files are loaded before running a command.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(untagged)]
+pub enum Dotenv {
+    /// `true` loads the default .env and .env.local files, `false` disables loading.
+    Enabled(bool),
+    /// The files to load, in order, relative to the configuration file.
+    Files(Vec<String>),
+}
+
+impl Default for Dotenv {
+    fn default() -> Self {
+        Dotenv::Enabled(true)
+    }
+}
+
+impl Dotenv {
+    /// Returns the dotenv files to load from a directory, in order.
+    ///
+    /// `${VAR}` references in file names are replaced with the value of the environment
+    /// variable, so `.env.${HOI_PROFILE}` selects a file per profile. Files that reference
+    /// an unset variable are left out.
+    ///
+    /// # Arguments
+    /// * `config_dir` - The directory relative file names are resolved against
+    pub fn files(&self, config_dir: &Path) -> Vec<PathBuf> {
+        let names = match self {
+            Dotenv::Enabled(false) => return Vec::new(),
+            Dotenv::Enabled(true) => default_dotenv_files(),
+            Dotenv::Files(files) => files.clone(),
+        };
+
+        names
+            .iter()
+            .filter_map(|name| expand_env_vars(name))
+            .map(|name| config_dir.join(name))
+            .collect()
+    }
+}
+
+/// An environment variable a command needs, written as a name or as a map of names to
+/// the regular expressions their values must match.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(untagged)]
+pub enum RequiredVar {
+    /// A variable that must be set to a non-empty value.
+    Name(String),
+    /// Variables that must be set to values matching a regular expression, by name.
+    Patterns(IndexMap<String, String>),
+}
+
+impl RequiredVar {
+    /// Returns the names of the variables with the pattern each must match, if any.
+    pub fn entries(&self) -> Vec<(&str, Option<&str>)> {
+        match self {
+            RequiredVar::Name(name) => vec![(name.as_str(), None)],
+            RequiredVar::Patterns(patterns) => patterns
+                .iter()
+                .map(|(name, pattern)| (name.as_str(), Some(pattern.as_str())))
+                .collect(),
+        }
+    }
+}
+
+/// Checks that required environment variables are set and match their patterns.
+///
+/// Variables set to an empty value count as missing. Patterns are regular expressions that
+/// match anywhere in the value unless anchored with `^` and `$`.
+///
+/// # Arguments
+/// * `required` - The required variables
+/// * `lookup` - Returns the value of a variable, or None if it is not set
+///
+/// # Returns
+/// * `Vec<String>` - A description of each variable that is missing or doesn't match
+pub fn check_required_vars(
+    required: &[RequiredVar],
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (name, pattern) in required.iter().flat_map(RequiredVar::entries) {
+        let Some(value) = lookup(name).filter(|value| !value.is_empty()) else {
+            problems.push(format!("{}: not set", name));
+            continue;
+        };
+
+        let Some(pattern) = pattern else {
+            continue;
+        };
+        match Regex::new(pattern) {
+            Ok(regex) if regex.is_match(&value) => {}
+            Ok(_) => problems.push(format!("{}: does not match {}", name, pattern)),
+            Err(e) => problems.push(format!("{}: invalid pattern {}: {}", name, pattern, e)),
+        }
+    }
+
+    problems
+}
+
+/// The result of loading dotenv files into the environment.
+#[derive(Debug, Default)]
+pub struct LoadedEnvironment {
+    /// The files that were loaded, in order.
+    pub files: Vec<PathBuf>,
+
+    /// The files that exist but could not be loaded, with the reason.
+    pub errors: Vec<(PathBuf, dotenvy::Error)>,
+}
+
+/// Loads environment variables from dotenv files into the process environment.
+///
+/// Files that do not exist are skipped. The first listed file does not override variables that
+/// are already set, and every later file overrides the variables set before it. With the default
+/// files this means .env is loaded first, and .env.local variables will override any variables
+/// with the same name defined in .env.
+///
+/// # Arguments
+/// * `files` - The dotenv files to load, in order
+///
+/// # Returns
+/// * `LoadedEnvironment` - The files that were loaded and the files that failed to load
+pub fn load_environment_files(files: &[PathBuf]) -> LoadedEnvironment {
+    let mut loaded = LoadedEnvironment::default();
+
+    for (index, file) in files.iter().enumerate() {
+        if !file.exists() {
+            continue;
+        }
+
+        let result = if index == 0 {
+            dotenvy::from_path(file)
+        } else {
+            dotenvy::from_path_override(file)
+        };
+
+        match result {
+            Ok(()) => loaded.files.push(file.clone()),
+            Err(e) => loaded.errors.push((file.clone(), e)),
+        }
+    }
+
+    loaded
+}
+
+/// Returns the dotenv files loaded when a configuration doesn't list any.
+fn default_dotenv_files() -> Vec<String> {
+    vec![".env".to_string(), ".env.local".to_string()]
+}
+
+/// Replaces `${VAR}` references with the value of the environment variable.
+/// Returns None if a referenced variable is unset or empty.
+fn expand_env_vars(value: &str) -> Option<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let end = start + rest[start..].find('}')?;
+        let value = env::var(&rest[start + 2..end])
+            .ok()
+            .filter(|v| !v.is_empty())?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Some(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use temp_env::with_vars;
+    use testdir::testdir;
+
+    #[test]
+    fn test_dotenv_files() {
+        let dir = Path::new("project");
+
+        assert_eq!(
+            Dotenv::default().files(dir),
+            vec![dir.join(".env"), dir.join(".env.local")]
+        );
+        assert!(Dotenv::Enabled(false).files(dir).is_empty());
+
+        let dotenv = Dotenv::Files(vec![
+            ".env".to_string(),
+            ".env.${HOI_TEST_PROFILE}".to_string(),
+        ]);
+        with_vars([("HOI_TEST_PROFILE", Some("staging"))], || {
+            assert_eq!(
+                dotenv.files(dir),
+                vec![dir.join(".env"), dir.join(".env.staging")]
+            );
+        });
+        with_vars([("HOI_TEST_PROFILE", None::<&str>)], || {
+            assert_eq!(dotenv.files(dir), vec![dir.join(".env")]);
+        });
+    }
+
+    #[test]
+    fn test_check_required_vars() {
+        let required: Vec<RequiredVar> = serde_yaml::from_str(
+            r#"
+- AWS_PROFILE
+- EMPTY
+- DATABASE_URL: "^postgres://"
+  REDIS_URL: "^redis://"
+- PORT: "[0-9"
+"#,
+        )
+        .unwrap();
+
+        let vars = IndexMap::from([
+            ("AWS_PROFILE", "dev"),
+            ("EMPTY", ""),
+            ("DATABASE_URL", "mysql://localhost/app"),
+            ("PORT", "8080"),
+        ]);
+        let problems = check_required_vars(&required, |name| {
+            vars.get(name).map(|value| value.to_string())
+        });
+
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert_eq!(problems[0], "EMPTY: not set");
+        assert_eq!(problems[1], "DATABASE_URL: does not match ^postgres://");
+        assert_eq!(problems[2], "REDIS_URL: not set");
+        assert!(problems[3].starts_with("PORT: invalid pattern [0-9: "));
+    }
+
+    #[test]
+    fn test_load_environment_files_reports_errors() {
+        let temp_dir = testdir!();
+        fs::write(temp_dir.join(".env"), "HOI_TEST_LOADED=first\n").unwrap();
+        fs::write(temp_dir.join(".env.broken"), "NOT VALID LINE\n").unwrap();
+        fs::write(temp_dir.join(".env.local"), "HOI_TEST_LOADED=last\n").unwrap();
+
+        with_vars([("HOI_TEST_LOADED", None::<&str>)], || {
+            let loaded = load_environment_files(&[
+                temp_dir.join(".env"),
+                temp_dir.join(".env.missing"),
+                temp_dir.join(".env.broken"),
+                temp_dir.join(".env.local"),
+            ]);
+
+            assert_eq!(
+                loaded.files,
+                vec![temp_dir.join(".env"), temp_dir.join(".env.local")]
+            );
+            assert_eq!(loaded.errors.len(), 1);
+            assert_eq!(loaded.errors[0].0, temp_dir.join(".env.broken"));
+            assert_eq!(env::var("HOI_TEST_LOADED").unwrap(), "last");
+        });
+    }
+}
@@ -0,0 +1,944 @@
+use crate::condition::Condition;
+use crate::config::Config;
+use crate::template;
+use crate::user_command::{Cmd, PassArgs, Step, UserCommand};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// The file formats commands can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A Makefile with a target per command. Arguments are passed with `ARGS="..."`.
+    Make,
+    /// A justfile with a recipe per command.
+    Just,
+    /// A self-contained bash script that dispatches on its first argument.
+    Sh,
+}
+
+impl ExportFormat {
+    /// The names of all export formats, as accepted by [`ExportFormat::from_name`].
+    pub const NAMES: [&'static str; 3] = ["make", "just", "sh"];
+
+    /// Looks up an export format by name.
+    ///
+    /// # Arguments
+    /// * `name` - One of [`ExportFormat::NAMES`]
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name {
+            "make" => Some(ExportFormat::Make),
+            "just" => Some(ExportFormat::Just),
+            "sh" => Some(ExportFormat::Sh),
+            _ => None,
+        }
+    }
+}
+
+/// A command prepared for export: the shell statements that set up its environment, the
+/// statement that runs it with the positional parameters as its arguments, and whether that
+/// is a single process.
+struct ExportedCommand<'a> {
+    name: String,
+    command: &'a UserCommand,
+    setup: Vec<String>,
+    run: String,
+    single: bool,
+}
+
+/// Exports the commands of a merged configuration to a Makefile, justfile or bash script
+/// that runs them the same way hoi does, so they can be used where hoi isn't installed.
+///
+/// Each command runs with its entrypoint, vars and argument placeholders filled in, its arguments
+/// passed as set by `pass_args`, its steps run one after another, its dotenv files loaded with
+/// the same precedence rules, and its `env` variables, `HOI_CONFIG_DIR` and `HOI_PROFILE` set.
+/// Paths inside the directory of the innermost configuration file are written relative to
+/// the exported file, which is expected to live in that directory. Commands restricted to
+/// other profiles than the active one are left out, and secrets are not masked.
+///
+/// # Arguments
+/// * `config` - The merged configuration, with the profile to export already applied
+/// * `format` - The format to export to
+pub fn export(config: &Config, format: ExportFormat) -> String {
+    let base_dir = config
+        .local_path()
+        .or(config.global_path.as_deref())
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let commands: Vec<ExportedCommand> = config
+        .hoi
+        .commands
+        .iter()
+        .filter(|(_, command)| config.hoi.can_run(command))
+        .map(|(name, command)| {
+            let (run, single) = run_statement(config, name, command, &base_dir, &mut Vec::new());
+            ExportedCommand {
+                name: name.clone(),
+                command,
+                setup: setup_statements(config, command, &base_dir),
+                run,
+                single,
+            }
+        })
+        .collect();
+
+    let header = format!(
+        "Generated by `hoi export`{}. Regenerate it instead of editing it by hand.",
+        match &config.hoi.active_profile {
+            Some(profile) => format!(" with the {} profile", profile),
+            None => String::new(),
+        }
+    );
+
+    match format {
+        ExportFormat::Make => export_make(config, &header, &commands),
+        ExportFormat::Just => export_just(config, &header, &commands),
+        ExportFormat::Sh => export_sh(config, &header, &commands),
+    }
+}
+
+/// Returns the statement that runs a command, given its arguments as the positional
+/// parameters of the shell, and whether it is a single process that can replace the shell.
+///
+/// Commands with steps run them one after another with `&&`, each preceded by its header,
+/// and steps that run another command run it in a subshell with its own environment.
+/// Commands with a matrix run in a loop over every combination of its values, one after another.
+fn run_statement(
+    config: &Config,
+    name: &str,
+    command: &UserCommand,
+    base_dir: &Path,
+    running: &mut Vec<String>,
+) -> (String, bool) {
+    let (statement, single) = match &command.cmd {
+        Cmd::Script(script) => (script_statement(config, name, command, script), true),
+        Cmd::Steps(steps) => (
+            steps_statement(config, name, command, steps, base_dir, running),
+            false,
+        ),
+    };
+
+    if command.r#if.is_none() && command.unless.is_none() && command.matrix.is_empty() {
+        return (statement, single);
+    }
+
+    let mut statement = statement;
+    if command.r#if.is_some() || command.unless.is_some() {
+        statement = guard_statement(
+            config,
+            name,
+            command,
+            (&command.r#if, &command.unless),
+            statement,
+            |reason| format!("Skipped command '{}' ({})", name, reason),
+            base_dir,
+        );
+    }
+    if !command.matrix.is_empty() {
+        statement = matrix_statement(command, statement);
+    }
+    (statement, false)
+}
+
+/// Wraps a statement in a loop over every combination of the matrix of a command, with the
+/// values exported as environment variables. Every combination runs, and the loop fails if
+/// any of them failed.
+fn matrix_statement(command: &UserCommand, statement: String) -> String {
+    let total: usize = command.matrix.values().map(Vec::len).product();
+    let names: Vec<&str> = command.matrix.keys().map(String::as_str).collect();
+    let label: Vec<String> = names
+        .iter()
+        .map(|name| format!("{}=${{{}}}", name, name))
+        .collect();
+
+    let mut body = format!(
+        "hoi_cell=$((hoi_cell + 1)); export {}; echo \"==> [matrix $hoi_cell/{}] {}\"; {{ {}; }} || hoi_failed=1",
+        names.join(" "),
+        total,
+        label.join(", "),
+        statement
+    );
+    for (name, values) in command.matrix.iter().rev() {
+        body = format!(
+            "for {} in {}; do {}; done",
+            name,
+            template::quote_words(values),
+            body
+        );
+    }
+
+    format!(
+        "( hoi_failed=0; hoi_cell=0; {}; [ \"$hoi_failed\" = 0 ] )",
+        body
+    )
+}
+
+/// Returns the variables available to the scripts of a command. The values of its matrix
+/// are only known when it runs, so they refer to the environment variables set by the loop.
+fn script_vars<'a>(config: &'a Config, command: &UserCommand) -> Cow<'a, IndexMap<String, String>> {
+    if command.matrix.is_empty() {
+        return Cow::Borrowed(&config.hoi.vars);
+    }
+
+    let mut vars = config.hoi.vars.clone();
+    vars.extend(
+        command
+            .matrix
+            .keys()
+            .map(|name| (name.clone(), format!("${{{}}}", name))),
+    );
+    Cow::Owned(vars)
+}
+
+/// Returns the statement that runs the steps of a command one after another.
+fn steps_statement(
+    config: &Config,
+    name: &str,
+    command: &UserCommand,
+    steps: &[Step],
+    base_dir: &Path,
+    running: &mut Vec<String>,
+) -> String {
+    running.push(name.to_string());
+    let total = steps.len();
+    let mut statements = Vec::new();
+
+    for (index, step) in steps.iter().enumerate() {
+        let title = step.title();
+        let step = step.definition();
+        let header = format!("==> [{}/{}] {}", index + 1, total, title);
+
+        let mut statement = match (&step.cmd, &step.run) {
+            (Some(script), None) => script_statement(config, name, command, script),
+            (None, Some(run)) => run_step_statement(config, run, &step.args, base_dir, running),
+            _ => format!(
+                "{{ echo {} >&2; false; }}",
+                template::shell_quote(&format!(
+                    "Step {} of command '{}' needs either a cmd or a run, but not both",
+                    index + 1,
+                    name
+                ))
+            ),
+        };
+
+        if step.dir.is_some() || !step.env.is_empty() {
+            let mut subshell: Vec<String> = step
+                .env
+                .iter()
+                .map(|(key, value)| format!("export {}={}", key, template::shell_quote(value)))
+                .collect();
+            if let Some(dir) = &step.dir {
+                let dir = command
+                    .dir()
+                    .map(|base| base.join(dir))
+                    .unwrap_or(dir.clone());
+                subshell.push(format!("cd {}", path_expression(&dir, base_dir)));
+            }
+            subshell.push(statement);
+            statement = format!("( {} )", subshell.join(" && "));
+        }
+
+        statement = format!("echo {} && {}", template::shell_quote(&header), statement);
+        if step.r#if.is_some() || step.unless.is_some() {
+            statement = guard_statement(
+                config,
+                name,
+                command,
+                (&step.r#if, &step.unless),
+                statement,
+                |reason| format!("{} (skipped, {})", header, reason),
+                base_dir,
+            );
+        }
+
+        if step.ignore_errors {
+            statement = format!(
+                "{{ {} || echo {} >&2; }}",
+                statement,
+                template::shell_quote(&format!(
+                    "Step {}/{} ({}) failed, continuing",
+                    index + 1,
+                    total,
+                    title
+                ))
+            );
+        }
+
+        statements.push(statement);
+    }
+
+    running.pop();
+    statements.join(" && ")
+}
+
+/// Wraps a statement so it only runs when its `if` condition holds and its `unless` condition
+/// doesn't, and prints why it was skipped otherwise.
+fn guard_statement(
+    config: &Config,
+    name: &str,
+    command: &UserCommand,
+    (r#if, unless): (&Option<Condition>, &Option<Condition>),
+    mut statement: String,
+    skipped: impl Fn(&str) -> String,
+    base_dir: &Path,
+) -> String {
+    let message = |kind: &str, condition: &Condition| {
+        let reason = format!("{}: {}", kind, condition.describe());
+        format!("echo {}", template::shell_quote(&skipped(&reason)))
+    };
+
+    if let Some(condition) = unless {
+        statement = format!(
+            "if {}; then {}; else {}; fi",
+            condition_test(config, name, command, condition, base_dir),
+            message("unless", condition),
+            statement
+        );
+    }
+    if let Some(condition) = r#if {
+        statement = format!(
+            "if {}; then {}; else {}; fi",
+            condition_test(config, name, command, condition, base_dir),
+            statement,
+            message("if", condition)
+        );
+    }
+
+    statement
+}
+
+/// Returns the shell test for a condition, which succeeds when the condition holds.
+///
+/// Shell tests run with the entrypoint of the command, and platforms are compared to the
+/// output of `uname -s`, or `$OS` on Windows.
+fn condition_test(
+    config: &Config,
+    name: &str,
+    command: &UserCommand,
+    condition: &Condition,
+    base_dir: &Path,
+) -> String {
+    let check = match condition {
+        Condition::Shell(test) => {
+            let test = template::render(test, &script_vars(config, command));
+            let line = config.hoi.entrypoint_line(name, command, test);
+            return format!("{} >/dev/null", template::quote_words(&line));
+        }
+        Condition::Check(check) => check,
+    };
+
+    let mut tests = Vec::new();
+    if let Some(var) = &check.env {
+        tests.push(format!("[ -n \"${{{}:-}}\" ]", var));
+    }
+    if let Some(file) = &check.file {
+        let path = command
+            .dir()
+            .map(|dir| dir.join(file))
+            .unwrap_or(file.clone());
+        tests.push(format!("[ -e {} ]", path_expression(&path, base_dir)));
+    }
+    if let Some(platform) = &check.platform {
+        tests.push(match platform.as_str() {
+            "windows" => "[ \"${OS:-}\" = Windows_NT ]".to_string(),
+            "unix" => "[ \"${OS:-}\" != Windows_NT ]".to_string(),
+            "macos" => "[ \"$(uname -s)\" = Darwin ]".to_string(),
+            platform => format!(
+                "[ \"$(uname -s | tr '[:upper:]' '[:lower:]')\" = {} ]",
+                template::shell_quote(platform)
+            ),
+        });
+    }
+
+    if tests.is_empty() {
+        return "true".to_string();
+    }
+    tests.join(" && ")
+}
+
+/// Returns the statement that runs a script of a command with its entrypoint.
+///
+/// Argument placeholders refer to the positional parameters of the shell the command runs in,
+/// which receives the arguments of the exported target, so they are filled in when it runs.
+fn script_statement(config: &Config, name: &str, command: &UserCommand, script: &str) -> String {
+    let mut cmd = template::render_command(
+        script,
+        &script_vars(config, command),
+        &command.args,
+        template::Args::Positional,
+    );
+
+    if command.pass_args() == PassArgs::Append {
+        cmd = format!("{} \"$@\"", cmd.trim_end());
+    }
+
+    let mut statement = template::quote_words(&config.hoi.entrypoint_line(name, command, cmd));
+    if passes_args(command) {
+        statement.push_str(" \"$@\"");
+    }
+    statement
+}
+
+/// Returns the statement for a step that runs another command: a subshell that sets up the
+/// environment of the other command and its arguments, and runs it.
+fn run_step_statement(
+    config: &Config,
+    command_name: &str,
+    args: &[String],
+    base_dir: &Path,
+    running: &mut Vec<String>,
+) -> String {
+    let fail =
+        |message: String| format!("{{ echo {} >&2; false; }}", template::shell_quote(&message));
+
+    let Some((name, command)) = config.hoi.find_command(command_name) else {
+        return fail(format!("Command not found: {}", command_name));
+    };
+    if running.iter().any(|running| running == name) {
+        return fail(format!(
+            "Commands run each other in a loop: {} -> {}",
+            running.join(" -> "),
+            name
+        ));
+    }
+
+    let mut statements = setup_statements(config, command, base_dir);
+    statements.push(
+        format!("set -- {}", template::quote_words(args))
+            .trim_end()
+            .to_string(),
+    );
+    if passes_args(command) {
+        statements.extend(set_args_statements(command));
+    }
+    statements.push(run_statement(config, name, command, base_dir, running).0);
+
+    format!("( {} )", statements.join("; "))
+}
+
+/// Returns true if the arguments of the exported target are passed to a command, either as
+/// set by `pass_args` or for its argument placeholders.
+fn passes_args(command: &UserCommand) -> bool {
+    command.pass_args() != PassArgs::None
+        || command
+            .cmd
+            .scripts()
+            .iter()
+            .any(|script| template::uses_args(script, &command.args))
+}
+
+/// Returns the shell statements that prepare the environment of a command: its dotenv files,
+/// its env variables, HOI_CONFIG_DIR and the directory it runs in, and HOI_PROFILE, in the
+/// order hoi applies them.
+fn setup_statements(config: &Config, command: &UserCommand, base_dir: &Path) -> Vec<String> {
+    let mut statements = Vec::new();
+
+    // The first dotenv file doesn't override variables that are already set; later files do
+    let files = config.environment_files(command);
+    if !files.is_empty() {
+        statements.push("hoi_saved=\"$(export -p)\"".to_string());
+    }
+    for (index, file) in files.iter().enumerate() {
+        let file = path_expression(file, base_dir);
+        statements.push(format!(
+            "set -a; if [ -f {file} ]; then . {file}; fi; set +a"
+        ));
+        if index == 0 {
+            statements.push("eval \"$hoi_saved\"".to_string());
+        }
+    }
+
+    for (key, value) in config.hoi.env.iter().chain(&command.env) {
+        statements.push(format!("export {}={}", key, template::shell_quote(value)));
+    }
+
+    if let Some(dir) = command.dir() {
+        statements.push(format!(
+            "export HOI_CONFIG_DIR={}",
+            path_expression(dir, base_dir)
+        ));
+
+        // Like with hoi, commands from the exported file's directory or a parent run there
+        if base_dir.starts_with(dir) {
+            statements.push("cd \"$HOI_CONFIG_DIR\"".to_string());
+        }
+    }
+    if let Some(profile) = &config.hoi.active_profile {
+        statements.push(format!(
+            "export HOI_PROFILE={}",
+            template::shell_quote(profile)
+        ));
+    }
+
+    statements
+}
+
+/// Renders a Makefile with a target per command.
+fn export_make(config: &Config, header: &str, commands: &[ExportedCommand]) -> String {
+    let mut make = format!("# {}\n", header);
+    if !config.hoi.description.is_empty() {
+        make.push_str(&format!("# {}\n", config.hoi.description));
+    }
+    make.push_str("#\n# Pass arguments to a command with ARGS, for example: make build ARGS=\"--release\"\n\n");
+    make.push_str("SHELL := bash\n");
+    make.push_str("HOI_EXPORT_DIR := $(abspath $(dir $(lastword $(MAKEFILE_LIST))))\n");
+    make.push_str("export HOI_EXPORT_DIR\n\n");
+
+    let targets: Vec<String> = commands.iter().map(|c| target_name(&c.name)).collect();
+    let has_help = targets.iter().any(|target| target == "help");
+    let mut phony = targets.clone();
+    if !has_help {
+        phony.insert(0, "help".to_string());
+    }
+    make.push_str(&format!(".PHONY: {}\n\n", phony.join(" ")));
+
+    // The first target is the default goal, so list the commands unless there's a help command
+    if !has_help {
+        make.push_str("help: ## Lists the available commands\n");
+        make.push_str("\t@echo \"Usage: make <command> [ARGS=\\\"...\\\"]\"\n");
+        for (target, exported) in targets.iter().zip(commands) {
+            let line = format!("  {:<20} {}", target, exported.command.description);
+            make.push_str(&format!(
+                "\t@echo {}\n",
+                make_escape(&template::shell_quote(&line))
+            ));
+        }
+        make.push('\n');
+    }
+
+    for (target, exported) in targets.iter().zip(commands) {
+        make.push_str(&target.to_string());
+        make.push(':');
+        if !exported.command.description.is_empty() {
+            make.push_str(&format!(
+                " ## {}",
+                exported.command.description.replace('\n', " ")
+            ));
+        }
+        make.push('\n');
+
+        // The arguments come from ARGS, which make fills in before the shell sees the recipe
+        let mut statements: Vec<String> = exported.setup.iter().map(|s| make_escape(s)).collect();
+        let command = exported.command;
+        if passes_args(command) {
+            let mut set_args = String::from("set --");
+            for arg in &command.prepend_args {
+                set_args.push_str(&format!(" {}", make_escape(&template::shell_quote(arg))));
+            }
+            if command.default_args.is_empty() {
+                set_args.push_str(" $(ARGS)");
+            } else {
+                set_args.push_str(&format!(
+                    " $(or $(ARGS),{})",
+                    make_escape(&template::quote_words(&command.default_args))
+                ));
+            }
+            statements.push(set_args);
+        }
+        statements.push(make_escape(&exported.run));
+        make.push_str(&format!("\t@{}\n", statements.join("; ")));
+
+        if let Some(alias) = &exported.command.alias {
+            make.push_str(&format!(
+                ".PHONY: {}\n{}: {}\n",
+                target_name(alias),
+                target_name(alias),
+                target
+            ));
+        }
+        make.push('\n');
+    }
+
+    make
+}
+
+/// Renders a justfile with a recipe per command.
+fn export_just(config: &Config, header: &str, commands: &[ExportedCommand]) -> String {
+    let mut just = format!("# {}\n", header);
+    if !config.hoi.description.is_empty() {
+        just.push_str(&format!("# {}\n", config.hoi.description));
+    }
+    just.push('\n');
+    just.push_str("set positional-arguments\n");
+    just.push_str("set shell := [\"bash\", \"-c\"]\n\n");
+    just.push_str("export HOI_EXPORT_DIR := justfile_directory()\n\n");
+    just.push_str("_default:\n    @just --justfile {{justfile()}} --list\n");
+
+    for exported in commands {
+        let recipe = target_name(&exported.name).replace(['.', '/'], "-");
+        just.push('\n');
+        if !exported.command.description.is_empty() {
+            just.push_str(&format!(
+                "# {}\n",
+                exported.command.description.replace('\n', " ")
+            ));
+        }
+        just.push_str(&format!("{} *args:\n", recipe));
+
+        let mut statements = exported.setup.clone();
+        if passes_args(exported.command) {
+            statements.extend(set_args_statements(exported.command));
+        }
+        statements.push(exported.run.clone());
+        just.push_str(&format!(
+            "    @{}\n",
+            statements.join("; ").replace("{{", "{{{{")
+        ));
+
+        if let Some(alias) = &exported.command.alias {
+            just.push_str(&format!(
+                "\nalias {} := {}\n",
+                target_name(alias).replace(['.', '/'], "-"),
+                recipe
+            ));
+        }
+    }
+
+    just
+}
+
+/// Renders a bash script that runs the command named by its first argument.
+fn export_sh(config: &Config, header: &str, commands: &[ExportedCommand]) -> String {
+    let mut sh = format!("#!/usr/bin/env bash\n# {}\n", header);
+    if !config.hoi.description.is_empty() {
+        sh.push_str(&format!("# {}\n", config.hoi.description));
+    }
+    sh.push_str("set -eo pipefail\n\n");
+    sh.push_str("HOI_EXPORT_DIR=\"$(cd \"$(dirname \"${BASH_SOURCE[0]}\")\" && pwd)\"\n");
+    sh.push_str("export HOI_EXPORT_DIR\n\n");
+
+    sh.push_str("usage() {\n    echo \"Usage: $(basename \"$0\") <command> [arguments...]\"\n");
+    sh.push_str("    echo\n    echo \"Commands:\"\n");
+    for exported in commands {
+        let name = match &exported.command.alias {
+            Some(alias) => format!("{} ({})", exported.name, alias),
+            None => exported.name.clone(),
+        };
+        let line = format!(
+            "  {:<20} {}",
+            name,
+            exported.command.description.replace('\n', " ")
+        );
+        sh.push_str(&format!("    echo {}\n", template::shell_quote(&line)));
+    }
+    sh.push_str("}\n\n");
+
+    sh.push_str("if [ $# -eq 0 ]; then\n    usage\n    exit 0\nfi\n\n");
+    sh.push_str("command=\"$1\"\nshift\n\ncase \"$command\" in\n");
+
+    for exported in commands {
+        let mut patterns = vec![template::shell_quote(&exported.name)];
+        if let Some(alias) = &exported.command.alias {
+            patterns.push(template::shell_quote(alias));
+        }
+        sh.push_str(&format!("    {})\n", patterns.join("|")));
+        let mut statements = exported.setup.clone();
+        if passes_args(exported.command) {
+            statements.extend(set_args_statements(exported.command));
+        }
+        for statement in &statements {
+            sh.push_str(&format!("        {}\n", statement));
+        }
+        if exported.single {
+            sh.push_str(&format!("        exec {}\n        ;;\n", exported.run));
+        } else {
+            sh.push_str(&format!("        {}\n        ;;\n", exported.run));
+        }
+    }
+
+    if !commands.iter().any(|c| c.name == "help") {
+        sh.push_str("    help | -h | --help)\n        usage\n        ;;\n");
+    }
+    sh.push_str("    *)\n        echo \"Unknown command: $command\" >&2\n        usage >&2\n        exit 1\n        ;;\nesac\n");
+
+    sh
+}
+
+/// Returns the shell statements that apply the `default_args` and `prepend_args` of a command
+/// to the positional parameters of the script or recipe.
+fn set_args_statements(command: &UserCommand) -> Vec<String> {
+    let mut statements = Vec::new();
+
+    if !command.default_args.is_empty() {
+        statements.push(format!(
+            "if [ $# -eq 0 ]; then set -- {}; fi",
+            template::quote_words(&command.default_args)
+        ));
+    }
+    if !command.prepend_args.is_empty() {
+        statements.push(format!(
+            "set -- {} \"$@\"",
+            template::quote_words(&command.prepend_args)
+        ));
+    }
+
+    statements
+}
+
+/// Returns a shell expression for a path, relative to the exported file when the path is
+/// inside `base_dir`.
+fn path_expression(path: &Path, base_dir: &Path) -> String {
+    match path.strip_prefix(base_dir) {
+        Ok(relative) if relative.as_os_str().is_empty() => "\"$HOI_EXPORT_DIR\"".to_string(),
+        Ok(relative) => format!(
+            "\"$HOI_EXPORT_DIR\"/{}",
+            template::shell_quote(&relative.to_string_lossy())
+        ),
+        Err(_) => template::shell_quote(&PathBuf::from(path).to_string_lossy()),
+    }
+}
+
+/// Escapes a recipe line for make, which would otherwise expand `$` itself.
+fn make_escape(line: &str) -> String {
+    line.replace('$', "$$")
+}
+
+/// Turns a command name into a target name, replacing characters make and just can't use.
+fn target_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+    use temp_env::with_var;
+    use testdir::testdir;
+
+    fn exported_config(dir: &Path) -> Config {
+        fs::write(
+            dir.join(".hoi.yml"),
+            r#"
+version: 1
+root: true
+description: "Export test"
+entrypoint: ["bash", "-c", "$@", "hoi"]
+vars:
+  greeting: Hello
+env:
+  SHARED: "from env"
+commands:
+  greet:
+    cmd: |
+      echo "{{greeting}} $FROM_DOTENV $LOCAL_ONLY $SHARED $PRESET"
+      echo "args: $*"
+    alias: g
+    description: "Greets with $HOME in the description"
+  costs:
+    cmd: echo 'costs $5 {{braces}}'
+    description: "Prints a price"
+  deploy:
+    cmd: echo "deploying {{version}} to {{target}}, then {{args[2:]}}"
+    args:
+      - name: target
+      - name: version
+        default: "latest \"stable\""
+  tag:
+    cmd: echo tagging
+    pass_args: append
+    prepend_args: ["--sign"]
+    default_args: ["latest", "two words"]
+  release:
+    cmd:
+      - echo "building $1"
+      - name: Publish
+        run: greet
+        args: ["from release"]
+      - cmd: exit 2
+        ignore_errors: true
+      - cmd: echo "in $(basename "$PWD") with $STAGE"
+        dir: sub
+        env:
+          STAGE: prod
+  gated:
+    cmd:
+      - cmd: echo "on CI"
+        if:
+          env: HOI_EXPORT_CI
+      - cmd: echo "without a lock file"
+        unless:
+          file: Cargo.lock
+      - run: never
+      - echo done
+  never:
+    cmd: echo never
+    if: test -n "$HOI_EXPORT_NEVER"
+  matrix:
+    matrix:
+      toolchain: [stable, beta]
+      features: ["", serde]
+    cmd: echo "{{toolchain}} [$features]" && test "$toolchain$features" != betaserde
+    unless: test "$features" = serde -a "$toolchain" = stable
+"#,
+        )
+        .unwrap();
+        fs::write(dir.join(".env"), "FROM_DOTENV=dotenv\nPRESET=from-dotenv\n").unwrap();
+        fs::write(dir.join(".env.local"), "LOCAL_ONLY=local\n").unwrap();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        with_var(env_var, Some(dir), || Config::discover(dir).unwrap())
+    }
+
+    #[test]
+    fn test_export_sh_runs_commands() {
+        let temp_dir = testdir!();
+        let config = exported_config(&temp_dir);
+
+        let script = temp_dir.join("hoi.sh");
+        fs::write(&script, export(&config, ExportFormat::Sh)).unwrap();
+
+        let output = Command::new("bash")
+            .arg(&script)
+            .args(["g", "one", "two words"])
+            .env("PRESET", "preset")
+            .current_dir(&temp_dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "Hello dotenv local from env preset\nargs: one two words\n"
+        );
+
+        let run = |args: &[&str]| {
+            let output = Command::new("bash")
+                .arg(&script)
+                .args(args)
+                .current_dir(&temp_dir)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        assert_eq!(
+            run(&["deploy", "prod"]),
+            "deploying latest \"stable\" to prod, then \n"
+        );
+        assert_eq!(
+            run(&["deploy", "prod", "1.2", "a b", "c"]),
+            "deploying 1.2 to prod, then a b c\n"
+        );
+        assert_eq!(run(&["tag", "v1", "it's"]), "tagging --sign v1 it's\n");
+        assert_eq!(run(&["tag"]), "tagging --sign latest two words\n");
+
+        fs::create_dir_all(temp_dir.join("sub")).unwrap();
+        assert_eq!(
+            run(&["release", "v1"]),
+            "==> [1/4] echo \"building $1\"\nbuilding v1\n\
+             ==> [2/4] Publish\nHello dotenv local from env from-dotenv\nargs: from release\n\
+             ==> [3/4] exit 2\n\
+             ==> [4/4] echo \"in $(basename \"$PWD\") with $STAGE\"\nin sub with prod\n"
+        );
+
+        let gated = "==> [1/4] echo \"on CI\" (skipped, if: env HOI_EXPORT_CI)\n\
+                     ==> [2/4] echo \"without a lock file\"\nwithout a lock file\n\
+                     ==> [3/4] hoi never\n\
+                     Skipped command 'never' (if: `test -n \"$HOI_EXPORT_NEVER\"`)\n\
+                     ==> [4/4] echo done\ndone\n";
+        assert_eq!(run(&["gated"]), gated);
+
+        // Every combination runs, and the matrix fails if one of them did
+        let output = Command::new("bash")
+            .arg(&script)
+            .arg("matrix")
+            .current_dir(&temp_dir)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "==> [matrix 1/4] toolchain=stable, features=\n\
+             stable []\n\
+             ==> [matrix 2/4] toolchain=stable, features=serde\n\
+             Skipped command 'matrix' (unless: `test \"$features\" = serde -a \"$toolchain\" = stable`)\n\
+             ==> [matrix 3/4] toolchain=beta, features=\n\
+             beta []\n\
+             ==> [matrix 4/4] toolchain=beta, features=serde\n\
+             beta [serde]\n"
+        );
+
+        let output = Command::new("bash")
+            .arg(&script)
+            .arg("missing")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+    }
+
+    #[test]
+    fn test_export_make_and_just() {
+        let temp_dir = testdir!();
+        let config = exported_config(&temp_dir);
+
+        let makefile = export(&config, ExportFormat::Make);
+        assert!(makefile.contains("greet: ## Greets with $HOME in the description\n"));
+        assert!(
+            makefile.contains("$$'echo \"Hello $$FROM_DOTENV"),
+            "{}",
+            makefile
+        );
+        assert!(makefile.contains("g: greet\n"));
+
+        let justfile = export(&config, ExportFormat::Just);
+        assert!(justfile.contains("# Greets with $HOME in the description\ngreet *args:\n"));
+        assert!(justfile.contains("alias g := greet\n"));
+        assert!(justfile.contains("\"$@\"\n"));
+        assert!(justfile.contains("{{{{braces}}"), "{}", justfile);
+
+        // Run the Makefile when make is available
+        if Command::new("make").arg("--version").output().is_ok() {
+            fs::write(temp_dir.join("Makefile"), makefile).unwrap();
+            let output = Command::new("make")
+                .args(["-s", "greet", "ARGS=one"])
+                .current_dir(&temp_dir)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                "Hello dotenv local from env from-dotenv\nargs: one\n"
+            );
+
+            let output = Command::new("make")
+                .args(["-s", "tag"])
+                .current_dir(&temp_dir)
+                .output()
+                .unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                "tagging --sign latest two words\n"
+            );
+
+            fs::write(temp_dir.join("Cargo.lock"), "").unwrap();
+            let output = Command::new("make")
+                .args(["-s", "gated"])
+                .env("HOI_EXPORT_CI", "true")
+                .env("HOI_EXPORT_NEVER", "1")
+                .current_dir(&temp_dir)
+                .output()
+                .unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                "==> [1/4] echo \"on CI\"\non CI\n\
+                 ==> [2/4] echo \"without a lock file\" (skipped, unless: file Cargo.lock)\n\
+                 ==> [3/4] hoi never\nnever\n\
+                 ==> [4/4] echo done\ndone\n"
+            );
+        }
+    }
+}
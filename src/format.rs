@@ -0,0 +1,129 @@
+use crate::diagnostic::invalid_config;
+use crate::hoi::HoiError;
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
+/// The file formats Hoi configuration files can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// All supported formats, in the order they are looked for during discovery.
+    pub const ALL: [ConfigFormat; 3] = [ConfigFormat::Yaml, ConfigFormat::Toml, ConfigFormat::Json];
+
+    /// Returns the file extension used for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yml",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+
+    /// Determines the format of a configuration file from its extension.
+    /// Files with an unknown or missing extension are treated as YAML.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the configuration file
+    pub fn from_path(path: &Path) -> ConfigFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    /// Parses the contents of a configuration file written in this format.
+    ///
+    /// # Arguments
+    /// * `contents` - The contents of the configuration file
+    ///
+    /// # Errors
+    /// * `HoiError::YamlParsing`, `HoiError::TomlParsing` or `HoiError::JsonParsing` -
+    ///   If the contents are invalid for this format
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, HoiError> {
+        Ok(match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(contents)?,
+            ConfigFormat::Toml => toml::from_str(contents)?,
+            ConfigFormat::Json => serde_json::from_str(contents)?,
+        })
+    }
+
+    /// Parses the contents of a configuration file, reporting errors with the file name
+    /// and a snippet pointing at the offending line.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the configuration file
+    /// * `contents` - The contents of the configuration file
+    ///
+    /// # Errors
+    /// * `HoiError::InvalidConfig` - If the contents are invalid for this format
+    pub fn parse_file<T: DeserializeOwned>(
+        self,
+        path: &Path,
+        contents: &str,
+    ) -> Result<T, HoiError> {
+        self.parse(contents)
+            .map_err(|error| invalid_config(path, contents, error))
+    }
+}
+
+/// Looks for a configuration file named `stem` with any supported extension in a directory,
+/// for example `.hoi.yml`, `.hoi.toml` or `.hoi.json` for the stem `.hoi`.
+/// When several exist, YAML is preferred over TOML, and TOML over JSON.
+///
+/// # Arguments
+/// * `dir` - The directory to look in
+/// * `stem` - The file name without its extension
+pub fn find_config_in_dir(dir: &Path, stem: &str) -> Option<PathBuf> {
+    ConfigFormat::ALL
+        .iter()
+        .map(|format| dir.join(format!("{}.{}", stem, format.extension())))
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hoi::Hoi;
+
+    #[test]
+    fn test_parse_all_formats() {
+        let yaml =
+            "version: 1\ncommands:\n  build:\n    cmd: cargo build\n  test:\n    cmd: cargo test\n";
+        let toml = "version = 1\n[commands.build]\ncmd = \"cargo build\"\n[commands.test]\ncmd = \"cargo test\"\n";
+        let json = r#"{"version": 1, "commands": {"build": {"cmd": "cargo build"}, "test": {"cmd": "cargo test"}}}"#;
+
+        for (format, contents) in [
+            (ConfigFormat::Yaml, yaml),
+            (ConfigFormat::Toml, toml),
+            (ConfigFormat::Json, json),
+        ] {
+            let hoi: Hoi = format.parse(contents).unwrap();
+            assert_eq!(hoi.version, "1");
+            assert_eq!(
+                hoi.commands.keys().collect::<Vec<_>>(),
+                vec!["build", "test"],
+                "{:?} should keep declaration order",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors_include_line() {
+        let result: Result<Hoi, _> = ConfigFormat::Toml.parse("[commands.build]\ncmd = \n");
+        let message = result.unwrap_err().to_string();
+        assert!(message.starts_with("TOML parsing error"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+
+        let result: Result<Hoi, _> = ConfigFormat::Json.parse("{\n  \"commands\": [\n}");
+        let message = result.unwrap_err().to_string();
+        assert!(message.starts_with("JSON parsing error"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+    }
+}
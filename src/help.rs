@@ -0,0 +1,458 @@
+use crate::config::Config;
+use crate::environment::RequiredVar;
+use crate::hoi::HoiError;
+use crate::runner::{matrix_value, Runner};
+use crate::template;
+use crate::user_command::{Cmd, Step, UserCommand};
+use std::fmt::Write;
+
+/// Renders the detailed help of a command: its descriptions, usage line, arguments,
+/// examples, environment variables, required tools and variables, the file it was defined
+/// in and the command it runs.
+///
+/// Values of secret environment variables are shown as `***`.
+///
+/// # Arguments
+/// * `config` - The merged configuration
+/// * `command_name` - The name or alias of the command
+///
+/// # Errors
+/// * `HoiError::CommandNotFound` - If no command has this name or alias
+pub fn command_help(config: &Config, command_name: &str) -> Result<String, HoiError> {
+    let (name, command) = config
+        .hoi
+        .find_command(command_name)
+        .ok_or_else(|| HoiError::CommandNotFound(command_name.to_string()))?;
+
+    // Writing to a String can't fail
+    let mut help = String::new();
+    match &command.alias {
+        Some(alias) => writeln!(help, "{} (alias: {})", name, alias).unwrap(),
+        None => writeln!(help, "{}", name).unwrap(),
+    }
+
+    if !command.description.is_empty() {
+        writeln!(help, "\n{}", command.description.trim_end()).unwrap();
+    }
+    if !command.help.is_empty() {
+        writeln!(help, "\n{}", command.help.trim_end()).unwrap();
+    }
+
+    writeln!(help, "\nUsage:\n  {}", usage(name, command)).unwrap();
+
+    if !command.args.is_empty() {
+        writeln!(help, "\nArguments:").unwrap();
+        let width = command
+            .args
+            .iter()
+            .map(|arg| arg.name.len())
+            .max()
+            .unwrap_or(0);
+        for arg in &command.args {
+            let mut details = Vec::new();
+            if arg.required {
+                details.push("required".to_string());
+            }
+            if let Some(default) = &arg.default {
+                details.push(format!("default: {}", default));
+            }
+
+            let mut line = format!("  {:<width$}  {}", arg.name, arg.description);
+            if !details.is_empty() {
+                line.push_str(&format!(" ({})", details.join(", ")));
+            }
+            writeln!(help, "{}", line.trim_end()).unwrap();
+        }
+    }
+
+    if !command.prepend_args.is_empty() {
+        writeln!(
+            help,
+            "\nAlways runs with: {}",
+            template::quote_words(&command.prepend_args)
+        )
+        .unwrap();
+    }
+    if !command.default_args.is_empty() {
+        writeln!(
+            help,
+            "\nWithout arguments, runs with: {}",
+            template::quote_words(&command.default_args)
+        )
+        .unwrap();
+    }
+
+    if !command.examples.is_empty() {
+        writeln!(help, "\nExamples:").unwrap();
+        for example in &command.examples {
+            writeln!(help, "  {}", example).unwrap();
+        }
+    }
+
+    let environment = environment_lines(config, command);
+    if !environment.is_empty() {
+        writeln!(help, "\nEnvironment:").unwrap();
+        for line in environment {
+            writeln!(help, "  {}", line).unwrap();
+        }
+    }
+
+    let requirements = Runner::new(&config.hoi).requirements(name);
+    if !requirements.is_empty() {
+        writeln!(help, "\nRequires: {}", requirements.join(", ")).unwrap();
+    }
+
+    let required_vars = required_vars(config, name);
+    if !required_vars.is_empty() {
+        writeln!(help, "\nRequired environment: {}", required_vars.join(", ")).unwrap();
+    }
+
+    let conditions: Vec<String> = [("if", &command.r#if), ("unless", &command.unless)]
+        .into_iter()
+        .filter_map(|(kind, condition)| {
+            condition
+                .as_ref()
+                .map(|condition| format!("{}: {}", kind, condition.describe()))
+        })
+        .collect();
+    if !conditions.is_empty() {
+        writeln!(help, "\nConditions: {}", conditions.join(", ")).unwrap();
+    }
+
+    if !command.matrix.is_empty() {
+        let parallel = if command.parallel {
+            " (in parallel)"
+        } else {
+            ""
+        };
+        writeln!(help, "\nMatrix{}:", parallel).unwrap();
+        for (name, values) in &command.matrix {
+            let values: Vec<String> = values.iter().map(|value| matrix_value(value)).collect();
+            writeln!(help, "  {}: {}", name, values.join(", ")).unwrap();
+        }
+    }
+
+    if !command.profiles.is_empty() {
+        writeln!(help, "\nProfiles: {}", command.profiles.join(", ")).unwrap();
+    }
+
+    if let Some(source) = &command.source {
+        writeln!(help, "\nDefined in: {}", source.display()).unwrap();
+    }
+
+    writeln!(help, "\nRuns:").unwrap();
+    match &command.cmd {
+        Cmd::Script(script) => {
+            for line in script.trim_end().lines() {
+                writeln!(help, "  {}", line).unwrap();
+            }
+        }
+        Cmd::Steps(steps) => {
+            for (index, step) in steps.iter().enumerate() {
+                writeln!(help, "{}", step_lines(index, step)).unwrap();
+            }
+        }
+    }
+
+    Ok(help)
+}
+
+/// Returns the environment variables a command needs, with the pattern each must match.
+fn required_vars(config: &Config, name: &str) -> Vec<String> {
+    let chain = config.hoi.command_chain(name);
+    let required = config
+        .hoi
+        .env_required
+        .iter()
+        .chain(chain.iter().flat_map(|(_, command)| &command.env_required));
+
+    let mut vars = Vec::new();
+    for (var, pattern) in required.flat_map(RequiredVar::entries) {
+        let var = match pattern {
+            Some(pattern) => format!("{} (matching {})", var, pattern),
+            None => var.to_string(),
+        };
+        if !vars.contains(&var) {
+            vars.push(var);
+        }
+    }
+    vars
+}
+
+/// Returns the usage line of a command, with required arguments in angle brackets
+/// and optional ones in square brackets.
+fn usage(name: &str, command: &UserCommand) -> String {
+    let mut usage = format!("hoi {}", name);
+
+    for arg in &command.args {
+        if arg.required {
+            usage.push_str(&format!(" <{}>", arg.name));
+        } else {
+            usage.push_str(&format!(" [{}]", arg.name));
+        }
+    }
+
+    if command.args.is_empty() {
+        usage.push_str(" (command arguments...)");
+    }
+
+    usage
+}
+
+/// Returns the lines describing a step: its number, its name if it has one, where it runs and
+/// whether it ignores errors, followed by what it runs.
+fn step_lines(index: usize, step: &Step) -> String {
+    let step = step.definition();
+
+    let mut body: Vec<String> = match (&step.run, &step.cmd) {
+        (Some(run), _) => vec![format!("hoi {} {}", run, template::quote_words(&step.args))
+            .trim_end()
+            .to_string()],
+        (None, Some(cmd)) => cmd.trim_end().lines().map(str::to_string).collect(),
+        (None, None) => Vec::new(),
+    };
+
+    let mut details = Vec::new();
+    if let Some(dir) = &step.dir {
+        details.push(format!("in {}", dir.display()));
+    }
+    if let Some(condition) = &step.r#if {
+        details.push(format!("if: {}", condition.describe()));
+    }
+    if let Some(condition) = &step.unless {
+        details.push(format!("unless: {}", condition.describe()));
+    }
+    if step.ignore_errors {
+        details.push("ignores errors".to_string());
+    }
+
+    let mut first = match &step.name {
+        Some(name) => name.clone(),
+        None if body.is_empty() => String::new(),
+        None => body.remove(0),
+    };
+    if !details.is_empty() {
+        first.push_str(&format!(" ({})", details.join(", ")));
+    }
+
+    let mut lines = vec![format!("  {}. {}", index + 1, first)];
+    lines.extend(body.iter().map(|line| format!("     {}", line)));
+    lines.join("\n")
+}
+
+/// Returns the environment variables a command sets, with their values, followed by the
+/// ones it reads from the environment.
+fn environment_lines(config: &Config, command: &UserCommand) -> Vec<String> {
+    let secrets = config.secret_names(command);
+    let mut lines = Vec::new();
+    let mut names = Vec::new();
+
+    for (key, value) in config.hoi.env.iter().chain(&command.env) {
+        let value = if secrets.contains(key) { "***" } else { value };
+        if let Some(index) = names.iter().position(|name| name == key) {
+            lines[index] = format!("{}={}", key, value);
+        } else {
+            names.push(key.clone());
+            lines.push(format!("{}={}", key, value));
+        }
+    }
+
+    for name in referenced_env_vars(&command.cmd.scripts().join("\n")) {
+        // Matrix values are set by hoi, and listed with the matrix
+        if !names.contains(&name) && !command.matrix.contains_key(&name) {
+            lines.push(format!("{} (from your environment)", name));
+            names.push(name);
+        }
+    }
+
+    lines
+}
+
+/// Returns the names of the environment variables a command references as `$NAME`
+/// or `${NAME}`, in order of first use.
+///
+/// # Arguments
+/// * `cmd` - The command to scan
+pub fn referenced_env_vars(cmd: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = cmd;
+
+    while let Some(start) = rest.find('$') {
+        rest = &rest[start + 1..];
+        let braced = rest.starts_with('{');
+        let name_start = usize::from(braced);
+
+        let name: String = rest[name_start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        let valid = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        if valid && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use temp_env::with_var;
+    use testdir::testdir;
+
+    #[test]
+    fn test_referenced_env_vars() {
+        assert_eq!(
+            referenced_env_vars("echo $HOME ${API_URL:-x} $1 $@ $$ $HOME $_private"),
+            vec!["HOME", "API_URL", "_private"]
+        );
+    }
+
+    #[test]
+    fn test_command_help() {
+        let temp_dir = testdir!();
+        let config_path = temp_dir.join(".hoi.yml");
+        fs::write(
+            &config_path,
+            r#"
+version: 1
+root: true
+secret: [API_TOKEN]
+requires: [git]
+env:
+  API_TOKEN: hunter2
+commands:
+  deploy:
+    cmd: |
+      ./deploy.sh "$TARGET" --token "$API_TOKEN" --user "$USER"
+    alias: d
+    description: "Deploys the app."
+    help: |
+      Builds a release and uploads it to the given target.
+    examples:
+      - hoi deploy staging
+    args:
+      - name: target
+        description: Where to deploy to
+        required: true
+      - name: version
+        description: The version to deploy
+        default: latest
+    prepend_args: ["--verbose"]
+    default_args: ["--dry-run", "two words"]
+    env:
+      TARGET: staging
+    requires: ["docker >= 20"]
+    env_required:
+      - DEPLOY_KEY
+      - DEPLOY_URL: "^https://"
+  release:
+    requires: [cargo]
+    if:
+      env: CI
+    matrix:
+      target: [x86_64-unknown-linux-gnu, aarch64-apple-darwin]
+      features: ["", serde]
+    parallel: true
+    cmd:
+      - cargo build --release
+      - name: Upload
+        cmd: |
+          ./upload.sh
+          ./notify.sh
+        dir: dist
+        unless: test -f dist/.skip
+        ignore_errors: true
+      - run: deploy
+        args: [prod]
+"#,
+        )
+        .unwrap();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        let config = with_var(env_var, Some(&temp_dir), || {
+            Config::discover(&temp_dir).unwrap()
+        });
+        let help = command_help(&config, "d").unwrap();
+
+        let expected = format!(
+            "deploy (alias: d)
+
+Deploys the app.
+
+Builds a release and uploads it to the given target.
+
+Usage:
+  hoi deploy <target> [version]
+
+Arguments:
+  target   Where to deploy to (required)
+  version  The version to deploy (default: latest)
+
+Always runs with: --verbose
+
+Without arguments, runs with: --dry-run 'two words'
+
+Examples:
+  hoi deploy staging
+
+Environment:
+  API_TOKEN=***
+  TARGET=staging
+  USER (from your environment)
+
+Requires: git, docker >= 20
+
+Required environment: DEPLOY_KEY, DEPLOY_URL (matching ^https://)
+
+Defined in: {}
+
+Runs:
+  ./deploy.sh \"$TARGET\" --token \"$API_TOKEN\" --user \"$USER\"
+",
+            config_path.display()
+        );
+        assert_eq!(help, expected);
+
+        let help = command_help(&config, "release").unwrap();
+        assert!(help.contains("\nRequires: git, cargo, docker >= 20\n"));
+        assert!(help.contains("\nConditions: if: env CI\n"), "{}", help);
+        assert!(
+            help.contains(
+                "\nMatrix (in parallel):
+  target: x86_64-unknown-linux-gnu, aarch64-apple-darwin
+  features: \"\", serde
+"
+            ),
+            "{}",
+            help
+        );
+        assert!(
+            help.ends_with(
+                "Runs:
+  1. cargo build --release
+  2. Upload (in dist, unless: `test -f dist/.skip`, ignores errors)
+     ./upload.sh
+     ./notify.sh
+  3. hoi deploy prod
+"
+            ),
+            "{}",
+            help
+        );
+
+        assert!(matches!(
+            command_help(&config, "missing"),
+            Err(HoiError::CommandNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::output::MASK;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The number of runs kept in the history. Older runs are dropped when a new one is recorded.
+pub const MAX_ENTRIES: usize = 1000;
+
+/// A single recorded run of a command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// The name of the command that ran, with aliases resolved.
+    pub command: String,
+
+    /// The arguments the command was run with, as given on the command line. `hoi last`
+    /// runs the command again with these. Secret values are replaced with `***`.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// The arguments the command ran with, after adding its `prepend_args` and
+    /// `default_args`. Secret values are replaced with `***`.
+    #[serde(default)]
+    pub resolved_args: Vec<String>,
+
+    /// The configuration file that defined the command.
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+
+    /// The directory hoi was run from.
+    pub cwd: PathBuf,
+
+    /// When the command started, in RFC 3339 format.
+    pub started_at: String,
+
+    /// How long the command ran for, in milliseconds.
+    #[serde(default)]
+    pub duration_ms: u64,
+
+    /// The exit code of the command, or None if it was terminated by a signal or skipped.
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+
+    /// Whether the command was skipped because of its `if` or `unless` condition.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+
+    /// The error that stopped the command before it could run, such as a missing tool or
+    /// environment variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The profile that was active, if any.
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Returns true if the command exited with code 0.
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Returns true if the command ran and didn't exit with code 0. Skipped runs haven't failed.
+    pub fn failed(&self) -> bool {
+        !self.skipped && !self.success()
+    }
+
+    /// Returns true if a secret value was masked in the arguments the command was run with,
+    /// so it can't be run again with them.
+    pub fn masked(&self) -> bool {
+        self.args
+            .iter()
+            .any(|arg| arg.as_bytes().windows(MASK.len()).any(|part| part == MASK))
+    }
+}
+
+/// The history of command runs, stored as one JSON object per line.
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// Creates a history stored in the given file. The file is created on the first run.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the history file
+    pub fn new(path: PathBuf) -> History {
+        History { path }
+    }
+
+    /// Returns the history stored in ~/.hoi/history.jsonl, or None if the home
+    /// directory can't be determined.
+    pub fn open_default() -> Option<History> {
+        dirs_next::home_dir().map(|home| History::new(home.join(".hoi").join("history.jsonl")))
+    }
+
+    /// Returns the path to the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns all recorded runs, oldest first. Lines that can't be parsed are skipped.
+    ///
+    /// # Errors
+    /// * `io::Error` - If the history file exists but can't be read
+    pub fn entries(&self) -> io::Result<Vec<HistoryEntry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Returns the most recent run started from a directory.
+    ///
+    /// # Arguments
+    /// * `cwd` - The directory the run was started from
+    ///
+    /// # Errors
+    /// * `io::Error` - If the history file exists but can't be read
+    pub fn last_in(&self, cwd: &Path) -> io::Result<Option<HistoryEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .find(|entry| entry.cwd == cwd))
+    }
+
+    /// Adds a run to the history, dropping the oldest runs beyond [`MAX_ENTRIES`].
+    ///
+    /// # Arguments
+    /// * `entry` - The run to record
+    ///
+    /// # Errors
+    /// * `io::Error` - If the history file can't be written
+    pub fn record(&self, entry: &HistoryEntry) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut entries = self.entries()?;
+        if entries.len() >= MAX_ENTRIES {
+            entries.drain(..=entries.len() - MAX_ENTRIES);
+            entries.push(entry.clone());
+
+            let mut contents = String::new();
+            for entry in &entries {
+                contents.push_str(&serde_json::to_string(entry)?);
+                contents.push('\n');
+            }
+            return fs::write(&self.path, contents);
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testdir::testdir;
+
+    fn entry(command: &str, cwd: &str, exit_code: i32) -> HistoryEntry {
+        HistoryEntry {
+            command: command.to_string(),
+            args: vec!["--release".to_string()],
+            resolved_args: vec!["--locked".to_string(), "--release".to_string()],
+            config: Some(PathBuf::from(cwd).join(".hoi.yml")),
+            cwd: PathBuf::from(cwd),
+            started_at: "2026-10-18T10:00:00+00:00".to_string(),
+            duration_ms: 1500,
+            exit_code: Some(exit_code),
+            skipped: false,
+            error: None,
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn test_record_and_read_history() {
+        let temp_dir = testdir!();
+        let history = History::new(temp_dir.join(".hoi").join("history.jsonl"));
+        assert!(history.entries().unwrap().is_empty());
+
+        history.record(&entry("build", "/project", 0)).unwrap();
+        history.record(&entry("test", "/other", 1)).unwrap();
+        history.record(&entry("lint", "/project", 0)).unwrap();
+
+        // Broken lines don't hide the rest of the history
+        let mut contents = fs::read_to_string(history.path()).unwrap();
+        contents.push_str("not json\n");
+        fs::write(history.path(), contents).unwrap();
+
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(!entries[1].success());
+        assert_eq!(entries[0].resolved_args, vec!["--locked", "--release"]);
+
+        let stopped = HistoryEntry {
+            exit_code: None,
+            error: Some("Command 'build' needs tools that aren't available".to_string()),
+            ..entry("build", "/project", 0)
+        };
+        assert!(stopped.failed());
+        assert!(!stopped.masked());
+
+        let masked = HistoryEntry {
+            args: vec!["--token=***".to_string()],
+            ..entry("deploy", "/project", 0)
+        };
+        assert!(masked.masked());
+        assert_eq!(
+            history.last_in(Path::new("/project")).unwrap(),
+            Some(entry("lint", "/project", 0))
+        );
+        assert_eq!(history.last_in(Path::new("/nowhere")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_record_drops_oldest_entries() {
+        let temp_dir = testdir!();
+        let history = History::new(temp_dir.join("history.jsonl"));
+
+        let lines: Vec<String> = (0..MAX_ENTRIES)
+            .map(|i| serde_json::to_string(&entry(&format!("cmd{}", i), "/project", 0)).unwrap())
+            .collect();
+        fs::write(history.path(), lines.join("\n") + "\n").unwrap();
+
+        history.record(&entry("newest", "/project", 0)).unwrap();
+
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].command, "cmd1");
+        assert_eq!(entries[MAX_ENTRIES - 1].command, "newest");
+    }
+}
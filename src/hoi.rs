@@ -1,7 +1,15 @@
-use crate::user_command::UserCommand;
+use crate::environment::{Dotenv, RequiredVar};
+use crate::logs::LogSettings;
+use crate::profile::Profile;
+use crate::template;
+use crate::user_command::{Cmd, PassArgs, UserCommand};
 use indexmap::IndexMap;
-use serde::Deserialize;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
 use std::io;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,26 +18,203 @@ pub enum HoiError {
     Io(#[from] io::Error),
     #[error("YAML parsing error: {0}")]
     YamlParsing(#[from] serde_yaml::Error),
+    #[error("TOML parsing error: {0}")]
+    TomlParsing(#[from] toml::de::Error),
+    #[error("JSON parsing error: {0}")]
+    JsonParsing(#[from] serde_json::Error),
     #[error("Command not found: {0}")]
     CommandNotFound(String),
-    #[error("No commands defined in .hoi.yml file. You need at least one command defined.")]
-    NoCommandsDefined,
+    #[error("Failed to parse {}: {message}", path.display())]
+    InvalidConfig { path: PathBuf, message: String },
+    #[error("Failed to read {}: {source}", path.display())]
+    UnreadableConfig { path: PathBuf, source: io::Error },
+    #[error("No commands defined in {}. You need at least one command defined.", .0.display())]
+    NoCommandsDefined(PathBuf),
+    #[error("Invalid override: {0}")]
+    InvalidOverride(String),
+    #[error("Profile not found: {0}. Available profiles: {1}")]
+    ProfileNotFound(String, String),
+    #[error("Don't know how to import commands from {}. Supported files are Makefiles, package.json, justfiles and Procfiles.", .0.display())]
+    UnsupportedImport(PathBuf),
+    #[error("Command '{0}' is internal and can only be run by other hoi commands")]
+    InternalCommand(String),
+    #[error("Template not found: {0}. Available templates: {1}")]
+    TemplateNotFound(String, String),
+    #[error("Step {step} of command '{command}' {message}")]
+    InvalidStep {
+        command: String,
+        step: usize,
+        message: String,
+    },
+    #[error("Commands run each other in a loop: {0}")]
+    CommandLoop(String),
+    #[error("Command '{command}' needs tools that are missing or out of date:\n{}\nInstall or update them, then run the command again.", bullet_list(problems))]
+    RequirementsNotMet {
+        command: String,
+        problems: Vec<String>,
+    },
+    #[error(
+        "Command '{command}' needs environment variables that are missing or invalid:\n{}\n{hint}",
+        bullet_list(problems)
+    )]
+    EnvironmentNotSet {
+        command: String,
+        problems: Vec<String>,
+        hint: String,
+    },
+    #[error("The matrix of command '{command}' {message}")]
+    InvalidMatrix { command: String, message: String },
+    #[error("Missing required argument <{argument}> for command: {command}")]
+    MissingArgument { command: String, argument: String },
+    #[error("Command '{command}' can only run with one of these profiles: {}", allowed.join(", "))]
+    ProfileNotAllowed {
+        command: String,
+        allowed: Vec<String>,
+    },
 }
 
-#[derive(Deserialize, Debug)]
-#[allow(dead_code)]
+/// A Hoi configuration file.
+#[derive(Deserialize, Debug, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Hoi {
-    #[serde(default = "default_version")]
-    pub(crate) version: String,
+    /// The version of the configuration file format.
+    #[serde(default = "default_version", deserialize_with = "string_or_number")]
+    #[schemars(schema_with = "version_schema")]
+    pub version: String,
 
+    /// A description of the command set, shown above the command listing.
     #[serde(default = "default_description")]
-    pub(crate) description: String,
+    pub description: String,
 
-    #[serde(default = "default_entrypoint")]
-    pub(crate) entrypoint: Vec<String>,
+    /// The program and arguments commands are run with. A "$@" argument is replaced
+    /// with the command; otherwise the command is appended.
+    #[serde(default)]
+    #[schemars(default = "default_entrypoint")]
+    pub entrypoint: Vec<String>,
+
+    /// The commands available in this configuration, keyed by name.
+    #[serde(default)]
+    pub commands: IndexMap<String, UserCommand>,
+
+    /// Variables that can be used in commands as `{{name}}`.
+    #[serde(default)]
+    pub vars: IndexMap<String, String>,
+
+    /// Environment variables set for every command.
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+
+    /// Tools every command needs, optionally with a version constraint, such as
+    /// `["docker", "node >= 18"]`. They are checked before a command runs.
+    #[serde(default)]
+    pub requires: Vec<String>,
+
+    /// Environment variables every command needs, as names or as maps of names to the
+    /// regular expressions their values must match, such as `[AWS_PROFILE, {DATABASE_URL:
+    /// "^postgres://"}]`. They are checked after the dotenv files are loaded.
+    #[serde(default)]
+    pub env_required: Vec<RequiredVar>,
+
+    /// Names of environment variables or vars whose values are replaced with `***`
+    /// in command output.
+    #[serde(default)]
+    pub secret: Vec<String>,
+
+    /// Treats every variable defined in a .env.local file as secret.
+    #[serde(default)]
+    pub secret_env_local: bool,
 
+    /// The order commands are listed in: "declared" (the default) keeps the order of the
+    /// configuration files, "alphabetical" sorts them by name, and "usage" lists the
+    /// most-used commands first.
     #[serde(default)]
-    pub(crate) commands: IndexMap<String, UserCommand>,
+    pub sort: CommandSort,
+
+    /// The greeting shown above the command listing: `false` hides it along with the
+    /// tip, and a string replaces it.
+    #[serde(default)]
+    pub banner: Option<Banner>,
+
+    /// Tips shown in the command listing instead of the built-in "Did you know?" facts.
+    #[serde(default)]
+    pub tips: Vec<String>,
+
+    /// When to color command names and aliases in the listing: "auto" (the default)
+    /// colors them when the output is a terminal and NO_COLOR is not set.
+    #[serde(default)]
+    pub color: ColorChoice,
+
+    /// Rotation and retention settings for command logs.
+    #[serde(default)]
+    pub logs: LogSettings,
+
+    /// Named sets of overrides, selected with `hoi --profile <name>` or `HOI_PROFILE`.
+    #[serde(default)]
+    pub profiles: IndexMap<String, Profile>,
+
+    /// The dotenv files to load before running a command, relative to this file.
+    /// Defaults to [".env", ".env.local"]; `false` turns loading off.
+    #[serde(default)]
+    pub dotenv: Option<Dotenv>,
+
+    /// Stops the search for Hoi configuration files in parent directories at this file.
+    #[serde(default)]
+    pub root: bool,
+
+    /// The name of the active profile, if one was selected.
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+}
+
+/// The order commands are listed in.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandSort {
+    /// The order the commands are declared in, global commands first.
+    #[default]
+    Declared,
+    /// Sorted by name, with groups sorted by name too.
+    Alphabetical,
+    /// The most-used commands first, according to the run history.
+    Usage,
+}
+
+/// The greeting shown above the command listing.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(untagged)]
+pub enum Banner {
+    /// `true` shows the default greeting, `false` hides the greeting and the tip.
+    Enabled(bool),
+    /// A greeting to show instead of the default one.
+    Text(String),
+}
+
+/// When to use colors in the command listing.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Colors when the output is a terminal and NO_COLOR is not set.
+    #[default]
+    Auto,
+    /// Always uses colors.
+    Always,
+    /// Never uses colors.
+    Never,
+}
+
+impl ColorChoice {
+    /// Looks up a color choice by name: "auto", "always" or "never".
+    ///
+    /// # Arguments
+    /// * `name` - The name of the color choice
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
 }
 
 impl Default for crate::hoi::Hoi {
@@ -39,8 +224,221 @@ impl Default for crate::hoi::Hoi {
             description: String::new(),
             entrypoint: Vec::new(),
             commands: IndexMap::new(),
+            vars: IndexMap::new(),
+            env: IndexMap::new(),
+            requires: Vec::new(),
+            env_required: Vec::new(),
+            secret: Vec::new(),
+            secret_env_local: false,
+            sort: CommandSort::default(),
+            banner: None,
+            tips: Vec::new(),
+            color: ColorChoice::default(),
+            logs: LogSettings::default(),
+            profiles: IndexMap::new(),
+            dotenv: None,
+            root: false,
+            active_profile: None,
+        }
+    }
+}
+
+impl Hoi {
+    /// Looks up a command by its alias.
+    ///
+    /// This function searches through all commands in the Hoi configuration
+    /// and returns the name of the command that has the specified alias.
+    ///
+    /// # Arguments
+    /// * `alias` - The alias to search for
+    ///
+    /// # Returns
+    /// * `Option<&str>` - The name of the command with the matching alias, or None if no match found
+    pub fn find_command_by_alias(&self, alias: &str) -> Option<&str> {
+        for (name, command) in &self.commands {
+            if let Some(a) = &command.alias {
+                if a == alias {
+                    return Some(name);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the entrypoint a command is run with: the command's own entrypoint,
+    /// then the configured one, then the platform default.
+    ///
+    /// # Arguments
+    /// * `command` - The command to get the entrypoint for
+    pub fn entrypoint_for(&self, command: &UserCommand) -> Vec<String> {
+        match &command.entrypoint {
+            Some(entrypoint) if !entrypoint.is_empty() => entrypoint.clone(),
+            _ if !self.entrypoint.is_empty() => self.entrypoint.clone(),
+            _ => default_entrypoint(),
+        }
+    }
+
+    /// Returns the program and arguments a script of a command is run with.
+    ///
+    /// Placeholders in the script are filled in with the vars and the shell-quoted arguments,
+    /// the arguments are passed on according to the `pass_args` setting of the command, and the
+    /// result is put in place of a "$@" argument of the entrypoint, or appended to it. When "$@"
+    /// is the last argument of the entrypoint, the command name follows it so a shell sees it
+    /// as $0 and the arguments as $1, $2, ...
+    ///
+    /// # Arguments
+    /// * `name` - The name of the command
+    /// * `command` - The command the script belongs to
+    /// * `script` - The script, either the `cmd` of the command or of one of its steps
+    /// * `vars` - The variables available to the script
+    /// * `args` - The arguments given on the command line
+    pub fn command_line(
+        &self,
+        name: &str,
+        command: &UserCommand,
+        script: &str,
+        vars: &IndexMap<String, String>,
+        args: &[String],
+    ) -> Vec<String> {
+        let mut cmd =
+            template::render_command(script, vars, &command.args, template::Args::Values(args));
+
+        let pass_args = command.pass_args();
+        if pass_args == PassArgs::Append && !args.is_empty() {
+            cmd = format!("{} {}", cmd.trim_end(), template::quote_words(args));
+        }
+
+        let mut command_line = self.entrypoint_line(name, command, cmd);
+        if pass_args == PassArgs::Positional {
+            command_line.extend_from_slice(args);
+        }
+
+        command_line
+    }
+
+    /// Puts a rendered command into the entrypoint of a command: in place of a "$@" argument,
+    /// followed by the command name as $0 if "$@" is the last argument, or at the end.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the command
+    /// * `command` - The command whose entrypoint to use
+    /// * `cmd` - The rendered command
+    pub fn entrypoint_line(&self, name: &str, command: &UserCommand, cmd: String) -> Vec<String> {
+        let mut entrypoint = self.entrypoint_for(command);
+
+        // Special handling for $@ in the entrypoint (replace with command)
+        match entrypoint.iter().position(|arg| arg == "$@") {
+            Some(index) => {
+                if index == entrypoint.len() - 1 {
+                    entrypoint.push(name.to_string());
+                }
+                entrypoint[index] = cmd;
+            }
+            // If $@ was not found in the entrypoint, just append the command
+            None => entrypoint.push(cmd),
         }
+
+        entrypoint
+    }
+
+    /// Returns true if a command may run with the active profile. Commands that don't
+    /// list any profiles can always run.
+    ///
+    /// # Arguments
+    /// * `command` - The command to check
+    pub fn can_run(&self, command: &UserCommand) -> bool {
+        command.profiles.is_empty()
+            || self
+                .active_profile
+                .as_ref()
+                .is_some_and(|profile| command.profiles.contains(profile))
+    }
+
+    /// Looks up a command by its alias or name, with aliases taking precedence.
+    ///
+    /// # Arguments
+    /// * `command_name` - The alias or name of the command
+    ///
+    /// # Returns
+    /// * `Option<(&str, &UserCommand)>` - The command name and definition, or None if no match found
+    pub fn find_command(&self, command_name: &str) -> Option<(&str, &UserCommand)> {
+        let name = self
+            .find_command_by_alias(command_name)
+            .unwrap_or(command_name);
+        self.commands
+            .get_key_value(name)
+            .map(|(name, command)| (name.as_str(), command))
     }
+
+    /// Returns a command followed by the commands its steps run, directly or through other
+    /// commands, each once and in the order they are first reached.
+    ///
+    /// # Arguments
+    /// * `command_name` - The alias or name of the command
+    ///
+    /// # Returns
+    /// * `Vec<(&str, &UserCommand)>` - The names and definitions of the commands, or nothing
+    ///   if no command matches
+    pub fn command_chain(&self, command_name: &str) -> Vec<(&str, &UserCommand)> {
+        let mut chain: Vec<(&str, &UserCommand)> = Vec::new();
+        let mut pending = vec![command_name.to_string()];
+
+        while let Some(command_name) = pending.pop() {
+            let Some((name, command)) = self.find_command(&command_name) else {
+                continue;
+            };
+            if chain.iter().any(|(visited, _)| *visited == name) {
+                continue;
+            }
+            chain.push((name, command));
+
+            if let Cmd::Steps(steps) = &command.cmd {
+                pending.extend(steps.iter().rev().filter_map(|step| step.definition().run));
+            }
+        }
+
+        chain
+    }
+}
+
+/// Formats problems as an indented list, one per line.
+fn bullet_list(problems: &[String]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("  - {}", problem))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Deserializes a value written as either a string or a number into a string.
+/// YAML accepts both for string fields, but TOML and JSON are strict about types.
+fn string_or_number<'a, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Integer(i64),
+        Float(f64),
+    }
+
+    Ok(match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) => s,
+        StringOrNumber::Integer(i) => i.to_string(),
+        StringOrNumber::Float(f) => f.to_string(),
+    })
+}
+
+/// Describes the version field, which may be written as a string or a number.
+fn version_schema(_gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = SchemaObject {
+        instance_type: Some(vec![InstanceType::String, InstanceType::Integer].into()),
+        ..SchemaObject::default()
+    };
+    schema.metadata().default = Some(default_version().into());
+    Schema::Object(schema)
 }
 
 /// Returns the default description string for Hoi configuration.
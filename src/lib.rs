@@ -0,0 +1,43 @@
+//! Hoi is a command-line tool to help create simple command-line powered utilities.
+//!
+//! Besides the `hoi` binary, this crate exposes the pieces the binary is built from so
+//! other tooling can discover, enumerate and run Hoi commands without shelling out:
+//!
+//! ```no_run
+//! use hoi::{Config, Runner};
+//!
+//! let config = Config::discover(&std::env::current_dir()?)?;
+//! for (name, command) in &config.hoi.commands {
+//!     println!("{}: {}", name, command.description);
+//! }
+//!
+//! let outcome = Runner::new(&config.hoi).run("build", &[])?;
+//! assert!(!outcome.failed());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod condition;
+pub mod config;
+pub mod diagnostic;
+pub mod environment;
+pub mod export;
+pub mod format;
+pub mod help;
+pub mod history;
+pub mod hoi;
+pub mod listing;
+pub mod logs;
+pub mod output;
+pub mod overrides;
+pub mod profile;
+pub mod requirements;
+pub mod runner;
+pub mod scaffold;
+pub mod schema;
+pub mod template;
+pub mod user_command;
+
+pub use crate::config::Config;
+pub use crate::hoi::{Hoi, HoiError};
+pub use crate::runner::{Invocation, Outcome, Runner};
+pub use crate::user_command::UserCommand;
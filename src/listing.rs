@@ -0,0 +1,300 @@
+use crate::config::Config;
+use crate::history::HistoryEntry;
+use crate::hoi::CommandSort;
+use crate::user_command::UserCommand;
+use std::cmp::Reverse;
+
+/// The commands built into hoi, with their descriptions. A user command with the same
+/// name takes precedence, except over `init`; see [`replaces_builtin`].
+pub const BUILTIN_COMMANDS: [(&str, &str); 8] = [
+    (
+        "init",
+        "Create a new .hoi.yml for the project in this directory. Use --template <name> or --global.",
+    ),
+    (
+        "schema",
+        "Print the JSON Schema for .hoi.yml configuration files.",
+    ),
+    (
+        "export",
+        "Print the commands as a Makefile, justfile or shell script: --format make|just|sh.",
+    ),
+    (
+        "import",
+        "Import the commands of a Makefile, package.json, justfile or Procfile.",
+    ),
+    (
+        "logs",
+        "Show the latest log of a command, or list the commands with logs.",
+    ),
+    (
+        "help",
+        "Show the detailed help of a command: hoi help <command>.",
+    ),
+    (
+        "history",
+        "Show previous runs. Use --failed to only show failed runs, or pass a command name.",
+    ),
+    (
+        "last",
+        "Run the previous command from this directory again with the same arguments.",
+    ),
+];
+
+/// Returns true if the configuration defines a command, or an alias, with the name of a
+/// built-in command, so that the user command runs instead of the built-in one.
+///
+/// `init` always runs, since it creates the configuration. When `help` is replaced,
+/// `hoi <command> --help` still shows the help of a command.
+///
+/// # Arguments
+/// * `config` - The merged configuration
+/// * `name` - The name of the built-in command
+pub fn replaces_builtin(config: &Config, name: &str) -> bool {
+    name != "init" && config.hoi.find_command(name).is_some()
+}
+
+/// Returns the built-in commands to list, leaving out the ones the configuration replaces.
+pub fn listed_builtins(config: &Config) -> Vec<(&'static str, &'static str)> {
+    BUILTIN_COMMANDS
+        .into_iter()
+        .filter(|(name, _)| !replaces_builtin(config, name))
+        .collect()
+}
+
+/// Where a command in the listing was defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandSource {
+    /// The global configuration in ~/.hoi.
+    Global,
+    /// The configuration file in the innermost directory.
+    Local,
+    /// A configuration file in a parent directory.
+    Parent,
+    /// A personal .hoi.local.yml overrides file.
+    Personal,
+}
+
+impl CommandSource {
+    /// Returns the label shown in the source column of the listing.
+    pub fn label(self) -> &'static str {
+        match self {
+            CommandSource::Global => "global",
+            CommandSource::Local => "local",
+            CommandSource::Parent => "parent",
+            CommandSource::Personal => "personal",
+        }
+    }
+}
+
+/// A user command as it appears in the listing.
+#[derive(Debug, Clone)]
+pub struct ListedCommand<'a> {
+    /// The name of the command.
+    pub name: &'a str,
+
+    /// The command definition.
+    pub command: &'a UserCommand,
+
+    /// Where the command was defined.
+    pub source: CommandSource,
+
+    /// Whether the command replaces a command with the same name from the global file.
+    pub shadows_global: bool,
+}
+
+/// A group of commands in the listing.
+#[derive(Debug, Clone)]
+pub struct Section<'a> {
+    /// The name of the group, or None for commands without a group.
+    pub title: Option<&'a str>,
+
+    /// The commands in the group, in the configured order.
+    pub commands: Vec<ListedCommand<'a>>,
+}
+
+/// Groups the visible commands of a configuration into sections by their `group`, in the
+/// order set by the `sort` setting. Commands without a group come first.
+///
+/// With `sort: declared` commands and groups keep the order of the configuration files,
+/// with `sort: alphabetical` both are sorted by name, and with `sort: usage` the most-used
+/// commands come first and groups are ordered by their most-used command.
+///
+/// # Arguments
+/// * `config` - The merged configuration
+/// * `history` - The recorded runs, used for `sort: usage`
+pub fn command_sections<'a>(config: &'a Config, history: &[HistoryEntry]) -> Vec<Section<'a>> {
+    let mut commands: Vec<ListedCommand> = config
+        .hoi
+        .commands
+        .iter()
+        .filter(|(_, command)| !command.is_hidden())
+        .map(|(name, command)| ListedCommand {
+            name,
+            command,
+            source: command_source(config, command),
+            shadows_global: config.shadows_global(name),
+        })
+        .collect();
+
+    match config.hoi.sort {
+        CommandSort::Declared => {}
+        CommandSort::Alphabetical => commands.sort_by(|a, b| a.name.cmp(b.name)),
+        CommandSort::Usage => {
+            // Count the runs of each command from the file that defines it; ties keep their order
+            commands.sort_by_cached_key(|listed| {
+                Reverse(
+                    history
+                        .iter()
+                        .filter(|entry| {
+                            entry.command == listed.name && entry.config == listed.command.source
+                        })
+                        .count(),
+                )
+            });
+        }
+    }
+
+    let mut sections: Vec<Section> = Vec::new();
+    for listed in commands {
+        let title = listed.command.group.as_deref();
+        match sections.iter_mut().find(|section| section.title == title) {
+            Some(section) => section.commands.push(listed),
+            None => sections.push(Section {
+                title,
+                commands: vec![listed],
+            }),
+        }
+    }
+
+    // Commands without a group come first; sorting is stable so groups otherwise keep
+    // the order of their first command
+    if config.hoi.sort == CommandSort::Alphabetical {
+        sections.sort_by(|a, b| a.title.cmp(&b.title));
+    } else {
+        sections.sort_by_key(|section| section.title.is_some());
+    }
+
+    sections
+}
+
+/// Determines where a command was defined from its source file.
+fn command_source(config: &Config, command: &UserCommand) -> CommandSource {
+    let Some(source) = command.source.as_deref() else {
+        return CommandSource::Local;
+    };
+
+    let is_personal = source
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(".hoi.local."));
+
+    if is_personal {
+        CommandSource::Personal
+    } else if config.global_path.as_deref() == Some(source) {
+        CommandSource::Global
+    } else if config.local_path() != Some(source)
+        && config
+            .local_paths
+            .iter()
+            .any(|path| path.as_path() == source)
+    {
+        CommandSource::Parent
+    } else {
+        CommandSource::Local
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use temp_env::with_var;
+    use testdir::testdir;
+
+    fn names<'a>(section: &Section<'a>) -> Vec<&'a str> {
+        section.commands.iter().map(|listed| listed.name).collect()
+    }
+
+    #[test]
+    fn test_command_sections() {
+        let temp_dir = testdir!();
+        let hoi_dir = temp_dir.join(".hoi");
+        let project_dir = temp_dir.join("project");
+        fs::create_dir_all(&hoi_dir).unwrap();
+        fs::create_dir_all(&project_dir).unwrap();
+
+        fs::write(
+            hoi_dir.join(".hoi.global.yml"),
+            "version: 1\ncommands:\n  update:\n    cmd: brew upgrade\n  test:\n    cmd: echo global\n",
+        )
+        .unwrap();
+        fs::write(
+            project_dir.join(".hoi.yml"),
+            r#"
+version: 1
+root: true
+sort: alphabetical
+commands:
+  test:
+    cmd: cargo test
+    group: Development
+  build:
+    cmd: cargo build
+    group: Development
+  deploy:
+    cmd: ./deploy.sh
+    group: Ops
+  logs:
+    cmd: docker compose logs
+    group: Ops
+  secret-helper:
+    cmd: echo hidden
+    hidden: true
+"#,
+        )
+        .unwrap();
+        fs::write(
+            project_dir.join(".hoi.local.yml"),
+            "commands:\n  scratch:\n    cmd: echo mine\n",
+        )
+        .unwrap();
+
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        let mut config = with_var(env_var, Some(&temp_dir), || {
+            Config::discover(&project_dir).unwrap()
+        });
+
+        let sections = command_sections(&config, &[]);
+        let titles: Vec<Option<&str>> = sections.iter().map(|s| s.title).collect();
+        assert_eq!(titles, vec![None, Some("Development"), Some("Ops")]);
+        assert_eq!(names(&sections[0]), vec!["scratch", "update"]);
+        assert_eq!(names(&sections[1]), vec!["build", "test"]);
+
+        assert_eq!(sections[0].commands[0].source, CommandSource::Personal);
+        assert_eq!(sections[0].commands[1].source, CommandSource::Global);
+        let test = &sections[1].commands[1];
+        assert_eq!(test.source, CommandSource::Local);
+        assert!(test.shadows_global);
+        assert!(!sections[1].commands[0].shadows_global);
+
+        // User commands replace the built-in commands with the same name, except init
+        assert!(replaces_builtin(&config, "logs"));
+        assert!(!replaces_builtin(&config, "history"));
+        let builtins: Vec<&str> = listed_builtins(&config)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert!(!builtins.contains(&"logs"));
+        assert!(builtins.contains(&"history"));
+
+        // Declaration order keeps the order of the files, with ungrouped commands first
+        config.hoi.sort = CommandSort::Declared;
+        let sections = command_sections(&config, &[]);
+        assert_eq!(names(&sections[0]), vec!["update", "scratch"]);
+        assert_eq!(names(&sections[1]), vec!["test", "build"]);
+    }
+}
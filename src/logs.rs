@@ -0,0 +1,256 @@
+use chrono::Local;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Rotation and retention settings for command logs.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct LogSettings {
+    /// How many log files to keep per command. Older files are removed after each run.
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+
+    /// Removes log files older than this many days after each run.
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            keep: default_keep(),
+            max_age_days: None,
+        }
+    }
+}
+
+/// Returns the default number of log files to keep per command.
+fn default_keep() -> usize {
+    10
+}
+
+/// A log file for a single run of a command, shared by its stdout and stderr.
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl LogFile {
+    /// Creates a new log file for a run at `<log_dir>/<command>/<timestamp>.log`.
+    ///
+    /// # Arguments
+    /// * `log_dir` - The directory all command logs are written to
+    /// * `command_name` - The name of the command that is about to run
+    pub fn create(log_dir: &Path, command_name: &str) -> io::Result<LogFile> {
+        let dir = log_dir.join(command_name);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("{}.log", Local::now().format("%Y%m%d-%H%M%S-%3f")));
+        let file = File::create(&path)?;
+
+        Ok(LogFile {
+            path,
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Returns the path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a line to the log, prefixed with the current time.
+    ///
+    /// # Arguments
+    /// * `line` - The line to write, without a trailing newline
+    pub fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        write!(file, "[{}] ", Local::now().format("%Y-%m-%d %H:%M:%S%.3f"))?;
+        file.write_all(line)?;
+        file.write_all(b"\n")
+    }
+
+    /// Returns a writer that timestamps each line written to it before adding it to the log.
+    /// Use one writer per output stream so lines from stdout and stderr are not mixed up.
+    pub fn writer(&self) -> LogWriter {
+        LogWriter {
+            log: self.clone(),
+            partial: Vec::new(),
+        }
+    }
+}
+
+/// Writes complete lines to a [`LogFile`], holding back a partial line until it is finished.
+/// A partial line left over when the writer is dropped is written as is.
+#[derive(Debug)]
+pub struct LogWriter {
+    log: LogFile,
+    partial: Vec<u8>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            self.log
+                .write_line(line[..end].strip_suffix(b"\r").unwrap_or(&line[..end]))?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        if !self.partial.is_empty() {
+            let _ = self.log.write_line(&self.partial);
+        }
+    }
+}
+
+/// Writes everything to two writers, such as the terminal and a log file.
+pub struct Tee<A, B>(pub A, pub B);
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(buf)?;
+        self.1.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        self.1.flush()
+    }
+}
+
+/// Returns the log files of a command, oldest first.
+///
+/// # Arguments
+/// * `log_dir` - The directory all command logs are written to
+/// * `command_name` - The name of the command
+pub fn command_logs(log_dir: &Path, command_name: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(log_dir.join(command_name)) else {
+        return Vec::new();
+    };
+
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+
+    // Log file names start with a sortable timestamp
+    logs.sort();
+    logs
+}
+
+/// Returns the most recent log file of a command, if there is one.
+///
+/// # Arguments
+/// * `log_dir` - The directory all command logs are written to
+/// * `command_name` - The name of the command
+pub fn latest_log(log_dir: &Path, command_name: &str) -> Option<PathBuf> {
+    command_logs(log_dir, command_name).pop()
+}
+
+/// Removes the log files of a command that fall outside the retention settings.
+///
+/// # Arguments
+/// * `log_dir` - The directory all command logs are written to
+/// * `command_name` - The name of the command
+/// * `settings` - How many files to keep and for how long
+pub fn apply_retention(
+    log_dir: &Path,
+    command_name: &str,
+    settings: &LogSettings,
+) -> io::Result<()> {
+    let logs = command_logs(log_dir, command_name);
+    let excess = logs.len().saturating_sub(settings.keep.max(1));
+    let max_age = settings
+        .max_age_days
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+
+    for (index, log) in logs.iter().enumerate() {
+        let expired = max_age.is_some_and(|max_age| {
+            fs::metadata(log)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age > max_age)
+        });
+
+        // Never remove the most recent log
+        if (index < excess || expired) && index + 1 < logs.len() {
+            fs::remove_file(log)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testdir::testdir;
+
+    #[test]
+    fn test_log_writer_timestamps_lines() {
+        let temp_dir = testdir!();
+        let log = LogFile::create(&temp_dir, "build").unwrap();
+
+        {
+            let mut writer = log.writer();
+            writer.write_all(b"first line\nsecond ").unwrap();
+            writer.write_all(b"line\r\nunfinished").unwrap();
+        }
+
+        let contents = fs::read_to_string(log.path()).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] first line"));
+        assert!(lines[1].ends_with("] second line"));
+        assert!(lines[2].ends_with("] unfinished"));
+        assert_eq!(latest_log(&temp_dir, "build").as_deref(), Some(log.path()));
+    }
+
+    #[test]
+    fn test_apply_retention() {
+        let temp_dir = testdir!();
+        let dir = temp_dir.join("build");
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "20260101-000000-000",
+            "20260102-000000-000",
+            "20260103-000000-000",
+        ] {
+            fs::write(dir.join(format!("{}.log", name)), name).unwrap();
+        }
+
+        let settings = LogSettings {
+            keep: 2,
+            max_age_days: None,
+        };
+        apply_retention(&temp_dir, "build", &settings).unwrap();
+
+        assert_eq!(
+            command_logs(&temp_dir, "build"),
+            vec![
+                dir.join("20260102-000000-000.log"),
+                dir.join("20260103-000000-000.log")
+            ]
+        );
+    }
+}
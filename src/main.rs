@@ -1,114 +1,31 @@
-mod hoi;
-mod user_command;
-
 use std::fs;
-use std::io::{BufReader, Read, Write};
-use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-
-use crate::hoi::{Hoi, HoiError};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use chrono::{DateTime, Local};
+use hoi::config::parse_config;
+use hoi::export::{export, ExportFormat};
+use hoi::format::{find_config_in_dir, ConfigFormat};
+use hoi::help::command_help;
+use hoi::history::{History, HistoryEntry};
+use hoi::hoi::{Banner, ColorChoice, CommandSort};
+use hoi::listing::{command_sections, listed_builtins, replaces_builtin};
+use hoi::logs::latest_log;
+use hoi::output::colors_enabled;
+use hoi::runner::{Outcome, PARENT_COMMAND_VAR};
+use hoi::scaffold::{
+    add_commands, detect_project, find_template, import_commands, render_config, Detection,
+    ScaffoldCommand, Template,
+};
+use hoi::template;
+use hoi::{Config, HoiError, Runner};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use tabled::builder::Builder;
 use tabled::settings::object::Columns;
-use tabled::settings::{Alignment, Modify, Padding, Style};
-
-/// Searches for a .hoi.yml configuration file in the current directory and its parents.
-/// Returns the path to the first .hoi.yml file found, or None if no configuration file exists.
-fn find_config_file() -> Option<PathBuf> {
-    use std::env;
-
-    let current_dir = env::current_dir().ok()?;
-    let mut dir = current_dir.as_path();
-
-    loop {
-        let config_path = dir.join(".hoi.yml");
-        if config_path.exists() {
-            // On Windows, avoid canonicalize() as it can lead to path format issues
-            #[cfg(not(windows))]
-            {
-                return config_path.canonicalize().ok().or(Some(config_path));
-            }
-
-            // For Windows, just return the path directly
-            #[cfg(windows)]
-            {
-                return Some(config_path);
-            }
-        }
-
-        if let Some(parent) = dir.parent() {
-            dir = parent;
-        } else {
-            break;
-        }
-    }
-
-    None
-}
-
-/// Checks for a global .hoi.global.yml configuration file in the user's home directory.
-/// Returns the path to the global config file if it exists, or None if it doesn't.
-fn find_global_config_file() -> Option<PathBuf> {
-    if let Some(home_dir) = dirs_next::home_dir() {
-        // Create the global config path
-        let global_config = home_dir.join(".hoi").join(".hoi.global.yml");
-
-        if global_config.exists() {
-            return match global_config.canonicalize() {
-                Ok(path) => Some(path),
-                Err(_) => Some(global_config),
-            };
-        }
-    }
-
-    None
-}
-
-/// Loads environment variables from .env and .env.local files in the same directory as the .hoi.yml file.
-/// If both files exist, .env is loaded first, and .env.local variables will override any variables
-/// with the same name defined in .env.
-///
-/// # Arguments
-/// * `config_dir` - The directory containing the .hoi.yml file
-fn load_environment_files(config_dir: &Path) {
-    let env_file = config_dir.join(".env");
-    if env_file.exists() {
-        let _ = dotenvy::from_path(&env_file);
-    }
-
-    let env_local_file = config_dir.join(".env.local");
-    if env_local_file.exists() {
-        let _ = dotenvy::from_path_override(&env_local_file);
-    }
-}
-
-/// Loads and parses the Hoi configuration file from the specified path.
-///
-/// # Arguments
-/// * `path` - The path to the .hoi.yml configuration file
-///
-/// # Returns
-/// * `Result<Hoi, HoiError>` - The parsed Hoi configuration struct or an error
-///
-/// # Errors
-/// * `HoiError::Io` - If the file cannot be read
-/// * `HoiError::YamlParsing` - If the YAML is invalid
-/// * `HoiError::NoCommandsDefined` - If the configuration doesn't define any commands
-fn load_config(path: &Path) -> Result<Hoi, HoiError> {
-    let file = fs::File::open(path)?;
-    let mut buf_reader = BufReader::new(file);
-    let mut contents = String::new();
-    buf_reader.read_to_string(&mut contents)?;
-
-    let hoi: Hoi = serde_yaml::from_str(&contents)?;
-
-    if hoi.commands.is_empty() {
-        return Err(HoiError::NoCommandsDefined);
-    }
-
-    Ok(hoi)
-}
+use tabled::settings::{Alignment, Modify, Padding, Style, Width};
 
 /// Returns a random "Did you know?" fact about Hoi.
 ///
@@ -123,7 +40,7 @@ fn get_random_did_you_know() -> &'static str {
         "In Dutch, 'hoi' is an informal way to say 'hi'.",
         "Hoi configuration files use YAML format.",
         "You can add custom commands to Hoi by editing your .hoi.yml file.",
-        "Hoi searches for .hoi.yml in your current directory and up through parent directories.",
+        "Hoi merges every .hoi.yml from your current directory up through parent directories.",
         "Hoi also looks for a global config at ~/.hoi/.hoi.global.yml.",
         "Global commands are available in all projects and mixed with local commands.",
         "You can add detailed descriptions to your commands in the .hoi.yml file.",
@@ -140,54 +57,297 @@ fn get_random_did_you_know() -> &'static str {
         .unwrap_or(&"Hoi is a command-line tool.")
 }
 
-/// Looks up a command by its alias.                                                                                                                                                                                                                                                                                                                                    
-///                                                                                                                                                                                                                                                                                                                                                                     
-/// This function searches through all commands in the Hoi configuration                                                                                                                                                                                                                                                                                                
-/// and returns the name of the command that has the specified alias.                                                                                                                                                                                                                                                                                                   
-///                                                                                                                                                                                                                                                                                                                                                                     
-/// # Arguments                                                                                                                                                                                                                                                                                                                                                         
-/// * `hoi` - The Hoi configuration struct containing command definitions                                                                                                                                                                                                                                                                                               
-/// * `alias` - The alias to search for                                                                                                                                                                                                                                                                                                                                 
-///                                                                                                                                                                                                                                                                                                                                                                     
-/// # Returns                                                                                                                                                                                                                                                                                                                                                          
-/// * `Option<&String>` - The name of the command with the matching alias, or None if no match found                                                                                                                                                                                                                                                                    
-fn find_command_by_alias(hoi: &Hoi, alias: &str) -> Option<String> {
-    for (name, command) in &hoi.commands {
-        if let Some(a) = &command.alias {
-            if a == alias {
-                return Some(name.clone());
+/// Options that come before the command name on the command line.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    /// The profile selected with `--profile <name>`.
+    profile: Option<String>,
+
+    /// Whether `--log` was given to write the output of the command to a log file.
+    log: bool,
+
+    /// Whether `--dry-run` was given to show what a command would run instead of running it.
+    dry_run: bool,
+
+    /// Whether `--no-banner` was given to hide the greeting and tip above the listing.
+    no_banner: bool,
+
+    /// The color choice given with `--color <auto|always|never>`.
+    color: Option<ColorChoice>,
+}
+
+/// Parses the options that come before the command name, removing them from `args`.
+///
+/// # Arguments
+/// * `args` - The command-line arguments, without the program name
+///
+/// # Returns
+/// * `Result<Options, Box<dyn std::error::Error>>` - The parsed options, or an error for unknown options
+fn parse_options(args: &mut Vec<String>) -> Result<Options, Box<dyn std::error::Error>> {
+    let mut options = Options::default();
+
+    while args.first().is_some_and(|arg| arg.starts_with("--")) {
+        let arg = args.remove(0);
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+
+        match flag.as_str() {
+            "--profile" => {
+                let value = match value {
+                    Some(value) => value,
+                    None if !args.is_empty() => args.remove(0),
+                    None => return Err("Missing profile name after --profile".into()),
+                };
+                options.profile = Some(value);
             }
+            "--log" if value.is_none() => options.log = true,
+            "--dry-run" if value.is_none() => options.dry_run = true,
+            "--no-banner" if value.is_none() => options.no_banner = true,
+            "--color" => {
+                let value = match value {
+                    Some(value) => value,
+                    None if !args.is_empty() => args.remove(0),
+                    None => return Err("Missing value after --color".into()),
+                };
+                options.color = Some(ColorChoice::from_name(&value).ok_or_else(|| {
+                    format!(
+                        "Invalid value for --color: {} (use auto, always or never)",
+                        value
+                    )
+                })?);
+            }
+            _ => return Err(format!("Unknown option: {}", flag).into()),
         }
     }
-    None
+
+    Ok(options)
 }
 
-/// Displays the available commands in a nicely formatted table.
+/// Prints the latest log of a command, or lists the commands that have logs when no
+/// command is given.
 ///
-/// This function generates and displays a table of all available commands
-/// defined in the Hoi configuration.
-/// It also shows a greeting and a random "Did you know?" fact.
+/// # Arguments
+/// * `config` - The merged configuration, used to find the log directory and resolve aliases
+/// * `command_name` - The name or alias of the command to show the log of
+///
+/// # Returns
+/// * `Result<(), Box<dyn std::error::Error>>` - Ok if the log was shown, or an error if there is none
+fn show_logs(
+    config: &Config,
+    command_name: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let log_dir = config
+        .log_dir()
+        .ok_or("Could not determine the log directory")?;
+
+    let Some(command_name) = command_name else {
+        let mut names: Vec<String> = fs::read_dir(&log_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_dir())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .filter(|name| latest_log(&log_dir, name).is_some())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+
+        if names.is_empty() {
+            println!("No logs found in {}", log_dir.display());
+        } else {
+            println!("Commands with logs in {}:", log_dir.display());
+            for name in names {
+                println!("  {}", name);
+            }
+        }
+        return Ok(());
+    };
+
+    // Logs are stored under the command name, so resolve aliases first
+    let name = config
+        .hoi
+        .find_command(command_name)
+        .map(|(name, _)| name)
+        .unwrap_or(command_name);
+
+    match latest_log(&log_dir, name) {
+        Some(path) => {
+            println!("==> {} <==", path.display());
+            print!("{}", fs::read_to_string(&path)?);
+            Ok(())
+        }
+        None => Err(format!("No logs found for command '{}'", name).into()),
+    }
+}
+
+/// Prints the recorded runs, most recent last.
 ///
 /// # Arguments
-/// * `hoi` - The Hoi configuration struct containing commands to display
-fn display_commands(hoi: &Hoi) {
-    let mut builder = Builder::default();
+/// * `config` - The merged configuration, used to resolve aliases
+/// * `history` - The history to show
+/// * `args` - The arguments after `history`: `--failed` and an optional command name or alias
+///
+/// # Returns
+/// * `Result<(), Box<dyn std::error::Error>>` - Ok if the history was shown, or an error
+fn show_history(
+    config: &Config,
+    history: &History,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut failed_only = false;
+    let mut command_name = None;
+    for arg in args {
+        match arg.as_str() {
+            "--failed" => failed_only = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg).into()),
+            _ => command_name = Some(arg.as_str()),
+        }
+    }
 
-    builder.push_record(["Command", "Alias", "Description"]);
+    // History is recorded under the command name, so resolve aliases first
+    let command_name = command_name.map(|name| {
+        config
+            .hoi
+            .find_command(name)
+            .map(|(name, _)| name)
+            .unwrap_or(name)
+    });
+
+    let entries: Vec<HistoryEntry> = history
+        .entries()?
+        .into_iter()
+        .filter(|entry| !failed_only || entry.failed())
+        .filter(|entry| command_name.is_none_or(|name| entry.command == name))
+        .collect();
+
+    if entries.is_empty() {
+        println!("No matching runs found in {}", history.path().display());
+        return Ok(());
+    }
+
+    let mut builder = Builder::default();
     builder.push_record([
-        "init",
-        "",
-        "Create a new .hoi.yml configuration file in the current directory.",
+        "Started",
+        "Command",
+        "Duration",
+        "Exit code",
+        "Profile",
+        "Directory",
     ]);
 
-    for (name, command) in &hoi.commands {
+    for entry in &entries {
+        let started_at = DateTime::parse_from_rfc3339(&entry.started_at)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| entry.started_at.clone());
+        // Entries recorded before resolved arguments were kept only have the given ones
+        let args = if entry.resolved_args.is_empty() {
+            &entry.args
+        } else {
+            &entry.resolved_args
+        };
+        let words: Vec<String> = std::iter::once(entry.command.clone())
+            .chain(args.iter().cloned())
+            .collect();
+        let command = template::quote_words(&words);
+        let exit_code = match entry.exit_code {
+            _ if entry.error.is_some() => "error".to_string(),
+            _ if entry.skipped => "skipped".to_string(),
+            Some(code) => code.to_string(),
+            None => "signal".to_string(),
+        };
+
         builder.push_record([
-            name,
-            command.alias.as_deref().unwrap_or(""),
-            &command.description,
+            started_at,
+            command,
+            format!("{:.1}s", entry.duration_ms as f64 / 1000.0),
+            exit_code,
+            entry.profile.clone().unwrap_or_default(),
+            entry.cwd.display().to_string(),
         ]);
     }
 
+    let mut table = builder.build();
+    table
+        .with(Style::blank())
+        .with(Padding::new(1, 1, 0, 0))
+        .with(Modify::new(Columns::new(..)).with(Alignment::left()));
+
+    println!("{}", table);
+    Ok(())
+}
+
+/// Returns true if the arguments run a built-in command: they start with its name, and the
+/// configuration, when there is one, doesn't define a command that replaces it.
+///
+/// # Arguments
+/// * `args` - The command line arguments, after hoi's own options
+/// * `name` - The name of the built-in command
+/// * `config` - The configuration, or None if it could not be loaded
+fn runs_builtin(args: &[String], name: &str, config: Option<&Config>) -> bool {
+    args.first().is_some_and(|arg| arg == name)
+        && !config.is_some_and(|config| replaces_builtin(config, name))
+}
+
+/// Displays the available commands in a nicely formatted table.
+///
+/// This function generates and displays a table of all available commands
+/// defined in the Hoi configuration, with a section per command group when
+/// commands have a `group`, and where each command was defined.
+/// It also shows a greeting and a random "Did you know?" fact.
+///
+/// # Arguments
+/// * `config` - The merged configuration containing the commands to display
+/// * `history` - The recorded runs, used to put the most-used commands first with `sort: usage`
+fn display_commands(config: &Config, history: &[HistoryEntry]) {
+    let hoi = &config.hoi;
+    let sections = command_sections(config, history);
+    let grouped = sections.iter().any(|section| section.title.is_some());
+
+    let mut builder = Builder::default();
+    builder.push_record(["Command", "Alias", "Source", "Description"]);
+
+    if grouped {
+        builder.push_record(["Built-in:", "", "", ""]);
+    }
+    let builtins = listed_builtins(config);
+    for (name, description) in &builtins {
+        builder.push_record([*name, "", "built-in", *description]);
+    }
+
+    for section in &sections {
+        if grouped {
+            builder.push_record(["", "", "", ""]);
+            builder.push_record([
+                format!("{}:", section.title.unwrap_or("Commands")),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+
+        for listed in &section.commands {
+            let source = if listed.shadows_global {
+                format!("{} (overrides global)", listed.source.label())
+            } else {
+                listed.source.label().to_string()
+            };
+
+            builder.push_record([
+                listed.name.to_string(),
+                listed.command.alias.clone().unwrap_or_default(),
+                source,
+                listed.command.description.clone(),
+            ]);
+        }
+    }
+
     let mut table = builder.build();
 
     table
@@ -195,226 +355,588 @@ fn display_commands(hoi: &Hoi) {
         .with(Padding::new(1, 1, 0, 0))
         .with(Modify::new(Columns::new(..)).with(Alignment::left()));
 
-    println!("Hoi Hoi!");
-    println!("\nDid you know? {}", get_random_did_you_know());
-    println!("\nUsage:");
-    println!("  hoi [command|alias] (command options) (command arguments...)");
+    // Wrap long descriptions to the width of the terminal
+    if let Some(width) = terminal_width() {
+        let rendered = table.to_string();
+        let description_start = rendered
+            .lines()
+            .next()
+            .and_then(|header| header.find("Description"));
+
+        if let Some(start) = description_start {
+            let available = width.saturating_sub(start + 1);
+            let too_wide = rendered.lines().any(|line| line.chars().count() > width);
+            if too_wide && available >= 20 {
+                table.with(
+                    Modify::new(Columns::single(3)).with(Width::wrap(available).keep_words(true)),
+                );
+            }
+        }
+    }
+
+    let mut rendered = table.to_string();
+    let color = use_color(hoi.color);
+    if color {
+        let names: Vec<&str> = builtins
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(
+                sections
+                    .iter()
+                    .flat_map(|s| s.commands.iter().map(|c| c.name)),
+            )
+            .collect();
+        let aliases: Vec<&str> = sections
+            .iter()
+            .flat_map(|s| s.commands.iter())
+            .filter_map(|c| c.command.alias.as_deref())
+            .collect();
+        rendered = colorize_listing(&rendered, &names, &aliases);
+    }
+
+    match &hoi.banner {
+        Some(Banner::Enabled(false)) => {}
+        banner => {
+            let greeting = match banner {
+                Some(Banner::Text(text)) => text.as_str(),
+                _ => "Hoi Hoi!",
+            };
+            if color {
+                println!("\x1b[1m{}\x1b[0m", greeting);
+            } else {
+                println!("{}", greeting);
+            }
+
+            let tip: &str = match hoi.tips.choose(&mut thread_rng()) {
+                Some(tip) => tip,
+                None => get_random_did_you_know(),
+            };
+            println!("\nDid you know? {}", tip);
+            println!();
+        }
+    }
+
+    println!("Usage:");
+    println!(
+        "  hoi [--profile <name>] [--log] [--dry-run] [--no-banner] [--color <when>] [command|alias] (command options) (command arguments...)"
+    );
 
     if !hoi.description.is_empty() {
-        println!("\n{}\n", hoi.description);
-    } else {
-        println!();
+        println!("\n{}", hoi.description);
+    }
+
+    let profiles = hoi.profiles.keys().cloned().collect::<Vec<_>>().join(", ");
+    match &hoi.active_profile {
+        Some(profile) if profiles.is_empty() => println!("\nProfile: {}", profile),
+        Some(profile) => println!("\nProfile: {} (available: {})", profile, profiles),
+        None if !profiles.is_empty() => println!(
+            "\nProfiles: {} (select one with --profile <name>)",
+            profiles
+        ),
+        None => {}
+    }
+
+    println!();
+
+    println!("{}\n", rendered);
+}
+
+/// Returns the width of the terminal hoi is writing to, or None if the output isn't a terminal.
+fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+
+    terminal_size::terminal_size().map(|(width, _)| usize::from(width.0))
+}
+
+/// Returns true if the listing should use colors. With `auto`, colors are used when the
+/// output is a terminal and the NO_COLOR environment variable is not set.
+///
+/// # Arguments
+/// * `choice` - The configured color choice
+fn use_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => colors_enabled(),
+    }
+}
+
+/// Colors command names and aliases in a rendered listing table.
+///
+/// The table is rendered without colors so the column widths are right, and the name and
+/// alias cells are colored afterwards, using the column positions of the header line.
+///
+/// # Arguments
+/// * `rendered` - The rendered table, starting with its header line
+/// * `names` - The command names to color
+/// * `aliases` - The aliases to color
+fn colorize_listing(rendered: &str, names: &[&str], aliases: &[&str]) -> String {
+    let mut lines = rendered.lines();
+    let Some(header) = lines.next() else {
+        return rendered.to_string();
+    };
+    let (Some(alias_start), Some(source_start)) = (header.find("Alias"), header.find("Source"))
+    else {
+        return rendered.to_string();
+    };
+
+    let mut colored = vec![header.to_string()];
+    for line in lines {
+        let chars: Vec<char> = line.chars().collect();
+        let cell = |from: usize, to: usize| -> String {
+            chars[from.min(chars.len())..to.min(chars.len())]
+                .iter()
+                .collect()
+        };
+
+        let name = cell(0, alias_start);
+        let alias = cell(alias_start, source_start);
+        let rest = cell(source_start, chars.len());
+
+        let name = match name.trim() {
+            text if names.contains(&text) => paint(&name, text, "1;36"),
+            text if text.ends_with(':') && alias.trim().is_empty() => paint(&name, text, "1"),
+            _ => name,
+        };
+        let alias = match alias.trim() {
+            text if !text.is_empty() && aliases.contains(&text) => paint(&alias, text, "33"),
+            _ => alias,
+        };
+
+        colored.push(format!("{}{}{}", name, alias, rest));
     }
 
-    println!("{}\n", table);
+    colored.join("\n")
+}
+
+/// Wraps the text of a table cell in an ANSI color code, keeping its padding.
+fn paint(cell: &str, text: &str, code: &str) -> String {
+    let start = cell.find(text).unwrap_or(0);
+    format!(
+        "{}\x1b[{}m{}\x1b[0m{}",
+        &cell[..start],
+        code,
+        text,
+        &cell[start + text.len()..]
+    )
 }
 
-/// Executes a command defined in the Hoi configuration.
+/// Creates a new Hoi configuration file.
 ///
-/// This function looks up the requested command in the Hoi configuration and executes it
-/// using the specified entrypoint and any additional arguments. It handles special
-/// placeholder substitution ($@) in the entrypoint and reports command execution status.
+/// Without options, the project in the current directory is detected from its Cargo.toml,
+/// package.json, Makefile or docker-compose.yml and a .hoi.yml with matching commands is
+/// created. When nothing is detected, a basic example template is used instead.
+/// It will not overwrite an existing file.
 ///
 /// # Arguments
-/// * `hoi` - The Hoi configuration struct containing command definitions
-/// * `command_name` - The name of the command to execute
-/// * `args` - Additional arguments to pass to the command
+/// * `args` - The arguments after `init`: `--template <name>` to use a built-in template or
+///   one from ~/.hoi/templates, and `--global` to create ~/.hoi/.hoi.global.yml
 ///
 /// # Returns
-/// * `Result<(), HoiError>` - Ok if the command executed successfully, or an error
+/// * `Result<(), Box<dyn std::error::Error>>` - Ok if file creation succeeded, or an error
 ///
 /// # Errors
-/// * `HoiError::CommandNotFound` - If the specified command is not defined in the configuration
-/// * `HoiError::Io` - If there's an IO error executing the command
-fn execute_command(hoi: &Hoi, command_name: &str, args: &[String]) -> Result<(), HoiError> {
-    let alias_or_command = match find_command_by_alias(hoi, command_name) {
-        Some(alias) => alias.to_string(),
-        None => command_name.parse().unwrap(),
-    };
+/// * Unknown options or templates, and various I/O errors if file creation fails
+fn create_init_config(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use std::env;
 
-    match hoi.commands.get(&alias_or_command) {
-        Some(command) => {
-            println!("Running command {}...", command_name);
-
-            // Start with entrypoint
-            let mut process_args: Vec<String> =
-                Vec::with_capacity(hoi.entrypoint.len() + args.len() + 1);
-
-            // Special handling for $@ in the entrypoint (replace with command)
-            let mut placeholder_found = false;
-            for arg in &hoi.entrypoint {
-                if arg == "$@" {
-                    process_args.push(command.cmd.to_string());
-                    placeholder_found = true;
-                } else {
-                    process_args.push(arg.clone());
-                }
-            }
+    let mut template_name = None;
+    let mut global = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.split_once('=') {
+            Some(("--template", name)) => template_name = Some(name.to_string()),
+            None if arg == "--template" => match args.next() {
+                Some(name) => template_name = Some(name.clone()),
+                None => return Err("Missing template name after --template".into()),
+            },
+            None if arg == "--global" => global = true,
+            _ => return Err(format!("Unknown option for init: {}", arg).into()),
+        }
+    }
 
-            // If $@ was not found in the entrypoint, just append the command
-            if !placeholder_found {
-                process_args.push(command.cmd.to_string());
-            }
+    let hoi_dir = dirs_next::home_dir().map(|home| home.join(".hoi"));
+    let (dir, stem) = if global {
+        let dir = hoi_dir
+            .clone()
+            .ok_or("Could not determine the home directory")?;
+        (dir, ".hoi.global")
+    } else {
+        (env::current_dir()?, ".hoi")
+    };
 
-            let entrypoint = process_args.remove(0);
-            process_args.extend_from_slice(args);
+    if let Some(existing) = find_config_in_dir(&dir, stem) {
+        println!(
+            "A {} file already exists at {}",
+            existing.file_name().unwrap_or_default().to_string_lossy(),
+            existing.display()
+        );
+        return Ok(());
+    }
 
-            let status = Command::new(entrypoint)
-                .args(&process_args)
-                .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit())
-                .status()?;
+    let templates_dir = hoi_dir.map(|dir| dir.join("templates"));
+    let template = match template_name {
+        Some(name) => find_template(&name, templates_dir.as_deref())?,
+        None => {
+            // Project detection only makes sense for a project configuration
+            let detection = if global {
+                Detection::default()
+            } else {
+                detect_project(&dir)
+            };
 
-            if !status.success() {
-                eprintln!(
-                    "Command '{}' failed with exit code: {:?}",
-                    command_name,
-                    status.code()
-                );
+            if detection.commands.is_empty() {
+                find_template("default", None)?
+            } else {
+                println!("Detected {}", detection.files.join(", "));
+                Template {
+                    contents: render_config("Project commands", &detection.commands),
+                    format: ConfigFormat::Yaml,
+                }
             }
-
-            Ok(())
         }
-        None => Err(HoiError::CommandNotFound(command_name.to_string())),
-    }
+    };
+
+    fs::create_dir_all(&dir)?;
+    let config_path = dir.join(format!("{}.{}", stem, template.format.extension()));
+    let mut file = fs::File::create(&config_path)?;
+    file.write_all(template.contents.as_bytes())?;
+
+    println!(
+        "Created new {} file at {}",
+        config_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+        config_path.display()
+    );
+    println!("Run 'hoi' to see your available commands.");
+
+    Ok(())
 }
 
-/// Creates a new .hoi.yml file with a basic template in the current directory.
+/// Imports the commands of a Makefile, package.json, justfile or Procfile into the
+/// configuration file in the current directory, creating a .hoi.yml if there is none.
+/// Commands that are already defined are left untouched.
 ///
-/// This function creates a new configuration file with some example commands
-/// to help users get started. It will not overwrite an existing file.
+/// # Arguments
+/// * `args` - The arguments after `import`: the path to the file to import
 ///
 /// # Returns
-/// * `Result<(), Box<dyn std::error::Error>>` - Ok if file creation succeeded, or an error
-///
-/// # Errors
-/// * Various I/O errors if file creation fails
-fn create_init_config() -> Result<(), Box<dyn std::error::Error>> {
-    use std::env;
-    use std::fs::File;
+/// * `Result<(), Box<dyn std::error::Error>>` - Ok if the commands were imported, or an error
+fn import_config(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [file] = args else {
+        return Err("Usage: hoi import <Makefile|package.json|justfile|Procfile>".into());
+    };
 
-    let current_dir = env::current_dir()?;
-    let config_path = current_dir.join(".hoi.yml");
+    let source = Path::new(file);
+    let commands = import_commands(source)?;
+    if commands.is_empty() {
+        println!("No commands found in {}", source.display());
+        return Ok(());
+    }
 
-    if config_path.exists() {
+    let current_dir = std::env::current_dir()?;
+    let Some(config_path) = find_config_in_dir(&current_dir, ".hoi") else {
+        let config_path = current_dir.join(".hoi.yml");
+        fs::write(&config_path, render_config("Project commands", &commands))?;
         println!(
-            "A .hoi.yml file already exists at {}",
+            "Imported {} commands from {} into {}",
+            commands.len(),
+            source.display(),
             config_path.display()
         );
         return Ok(());
+    };
+
+    // Never clobber commands that are already defined
+    let existing = parse_config(&config_path)?;
+    let (skipped, added): (Vec<ScaffoldCommand>, Vec<ScaffoldCommand>) = commands
+        .into_iter()
+        .partition(|command| existing.commands.contains_key(&command.name));
+
+    if !added.is_empty() {
+        let contents = fs::read_to_string(&config_path)?;
+        fs::write(&config_path, add_commands(&config_path, &contents, &added)?)?;
     }
 
-    let mut file = File::create(&config_path)?;
-
-    let template = r#"version: 1
-description: "Custom commands"
-commands:
-  hello:
-    cmd: echo "Hello from Hoi!"
-    description: "A simple example command."
-  multiline:
-    cmd: |
-      echo "This is a multi-line command"
-      echo "Each line will be executed in sequence"
-    alias: ml
-    description: "An example of a multi-line command with an alias."
-  help:
-    cmd: |
-      echo "To add new commands, edit the .hoi.yml file in this directory."
-      echo "Run 'hoi' to see a list of all available commands."
-    description: "Shows help information about using hoi."
-"#;
-
-    file.write_all(template.as_bytes())?;
-    println!("Created new .hoi.yml file at {}", config_path.display());
-    println!("Run 'hoi' to see your available commands.");
+    println!(
+        "Imported {} commands from {} into {}",
+        added.len(),
+        source.display(),
+        config_path.display()
+    );
+    if !skipped.is_empty() {
+        let names: Vec<&str> = skipped.iter().map(|c| c.name.as_str()).collect();
+        println!("Skipped commands that already exist: {}", names.join(", "));
+    }
 
     Ok(())
 }
 
 /// The main entry point for the Hoi application.
 ///
+/// Runs the application and prints any error with its full description, so that
+/// configuration errors show the file name and the offending line.
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the Hoi application.
+///
 /// This function coordinates the overall flow of the application:
-/// 1. Finds and loads the Hoi configuration files (local and global)
-/// 2. Loads environment variables from .env and .env.local files if they exist
-///    (with .env.local values overriding .env values)
-/// 3. Merges configurations, with local commands taking precedence
-/// 4. Parses command-line arguments
-/// 5. Either displays available commands or executes the specified command
+/// 1. Parses command-line arguments
+/// 2. Finds, loads and merges the Hoi configuration files (global and local),
+///    with local commands taking precedence
+/// 3. Activates the profile selected with --profile or HOI_PROFILE, if any
+/// 4. Either displays available commands or executes the specified command, after
+///    loading the dotenv files for it (.env and .env.local by default, with .env.local
+///    values overriding .env values)
 ///
 /// # Returns
 /// * `Result<(), Box<dyn std::error::Error>>` - Ok if execution was successful or an error
 ///
 /// # Errors
 /// * Various errors can be returned if configuration loading or command execution fails
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn run() -> Result<(), Box<dyn std::error::Error>> {
     use std::env;
 
     // Parse command line arguments early to check for 'init' command
     let mut args: Vec<String> = env::args().skip(1).collect();
+    let options = parse_options(&mut args)?;
 
     // Handle the 'init' command before looking for config files
     if !args.is_empty() && args[0] == "init" {
-        return create_init_config();
+        return create_init_config(&args[1..]);
     }
 
-    // Find and load the local config file (project-specific)
-    let local_config_path = find_config_file();
+    // Find and merge the global and local config files. Built-in commands that don't need
+    // them still run when they can't be loaded
+    let current_dir = env::current_dir()?;
+    let discovered = Config::discover(&current_dir);
 
-    // Find and load the global config file
-    let global_config_path = find_global_config_file();
+    // Import commands from another task runner, which may create the first config file
+    if runs_builtin(&args, "import", discovered.as_ref().ok()) {
+        return import_config(&args[1..]);
+    }
 
-    // Start with empty default configuration
-    let mut merged_hoi = Hoi::default();
+    // Print the JSON Schema for the configuration format
+    if runs_builtin(&args, "schema", discovered.as_ref().ok()) {
+        println!("{}", hoi::schema::config_schema_json());
+        return Ok(());
+    }
 
-    // Load and merge global config if it exists
-    if let Some(global_path) = global_config_path {
-        if let Ok(global_hoi) = load_config(&global_path) {
-            if !global_hoi.entrypoint.is_empty() {
-                merged_hoi.entrypoint = global_hoi.entrypoint;
-            }
+    let mut config = discovered?;
+    let history = History::open_default();
+
+    // Re-run the previous command from this directory with the same arguments
+    let mut last_profile = None;
+    if runs_builtin(&args, "last", Some(&config)) {
+        let entry = match &history {
+            Some(history) => history.last_in(&current_dir)?,
+            None => None,
+        };
+        let Some(entry) = entry else {
+            return Err("No previous command has been run from this directory".into());
+        };
+        if entry.masked() {
+            return Err(format!(
+                "The previous command was run with secret arguments that weren't recorded. \
+                 Run `hoi {}` again with their real values instead.",
+                entry.command
+            )
+            .into());
+        }
 
-            if !global_hoi.description.is_empty() && merged_hoi.description.is_empty() {
-                merged_hoi.description = global_hoi.description;
-            }
+        args = std::iter::once(entry.command).chain(entry.args).collect();
+        last_profile = entry.profile;
+    }
 
-            for (name, command) in global_hoi.commands {
-                merged_hoi.commands.insert(name, command);
+    // Activate the profile selected with --profile or HOI_PROFILE, or the one the
+    // previous run used when re-running it
+    let explicit_profile = options.profile.is_some();
+    let profile = options
+        .profile
+        .or_else(|| env::var("HOI_PROFILE").ok().filter(|p| !p.is_empty()))
+        .or(last_profile);
+    if let Some(profile) = profile {
+        match config.apply_profile(&profile) {
+            Ok(()) => env::set_var("HOI_PROFILE", &profile),
+            // HOI_PROFILE may be set for another project, such as the one a command of another
+            // hoi configuration runs in, so only a profile given with --profile has to exist
+            Err(e @ HoiError::ProfileNotFound(..)) if !explicit_profile => {
+                eprintln!("Warning: {}. Continuing without a profile.", e);
             }
+            Err(e) => return Err(e.into()),
         }
     }
 
-    // Load and merge local config if it exists (overriding global settings)
-    if let Some(local_path) = local_config_path {
-        if let Some(config_dir) = local_path.parent() {
-            load_environment_files(config_dir);
-        }
-
-        if let Ok(local_hoi) = load_config(&local_path) {
-            // Override entrypoint if defined in local config
-            if !local_hoi.entrypoint.is_empty() {
-                merged_hoi.entrypoint = local_hoi.entrypoint;
-            }
+    // Show the latest log of a command
+    if runs_builtin(&args, "logs", Some(&config)) {
+        return show_logs(&config, args.get(1).map(String::as_str));
+    }
 
-            // Override description if defined in local config
-            if !local_hoi.description.is_empty() {
-                merged_hoi.description = local_hoi.description;
-            }
+    // Export the commands for use without hoi
+    if runs_builtin(&args, "export", Some(&config)) {
+        let format = match args.get(1).map(String::as_str) {
+            Some("--format") => args.get(2).map(String::as_str),
+            Some(arg) => arg.strip_prefix("--format="),
+            None => None,
+        };
+        let Some(format) = format.and_then(ExportFormat::from_name) else {
+            return Err(format!(
+                "Usage: hoi export --format {}",
+                ExportFormat::NAMES.join("|")
+            )
+            .into());
+        };
+
+        print!("{}", export(&config, format));
+        return Ok(());
+    }
 
-            // Add local commands (overriding any global commands with the same name)
-            for (name, command) in local_hoi.commands {
-                merged_hoi.commands.insert(name, command);
-            }
+    // Show the detailed help of a command, or list the commands without a command name
+    if runs_builtin(&args, "help", Some(&config)) {
+        if let Some(command_name) = args.get(1) {
+            print!("{}", command_help(&config, command_name)?);
+            return Ok(());
         }
+        args.clear();
+    }
+
+    // Browse the history of previous runs
+    if runs_builtin(&args, "history", Some(&config)) {
+        let history = history.ok_or("Could not determine the home directory")?;
+        return show_history(&config, &history, &args[1..]);
     }
 
     // Args were already parsed earlier to check for 'init' command
     if args.is_empty() {
-        display_commands(&merged_hoi);
+        // Options given on the command line take precedence over the configuration
+        if options.no_banner {
+            config.hoi.banner = Some(Banner::Enabled(false));
+        }
+        if let Some(color) = options.color {
+            config.hoi.color = color;
+        }
+
+        // The history is only needed to sort by usage
+        let entries = match &history {
+            Some(history) if config.hoi.sort == CommandSort::Usage => {
+                history.entries().unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+        display_commands(&config, &entries);
     } else {
         let command_name = args.remove(0);
-        execute_command(&merged_hoi, &command_name, &args)?;
+        let Some((name, command)) = config.hoi.find_command(&command_name) else {
+            return Err(HoiError::CommandNotFound(command_name).into());
+        };
+
+        // Internal commands only run from other commands, which set the parent command. Steps
+        // that run them with `run:` don't come through here
+        if command.internal && env::var_os(PARENT_COMMAND_VAR).is_none() {
+            return Err(HoiError::InternalCommand(name.to_string()).into());
+        }
+
+        // Commands that don't declare arguments leave --help to hoi
+        if command.args.is_empty() && args == ["--help"] {
+            print!("{}", command_help(&config, &command_name)?);
+            return Ok(());
+        }
+
+        // Load the dotenv files for the command, warning about files that can't be parsed
+        for (path, e) in config.load_environment(command).errors {
+            eprintln!("Warning: failed to load {}: {}", path.display(), e);
+        }
+
+        let mut runner = Runner::new(&config.hoi)
+            .with_secrets(config.secret_names(command))
+            .with_environment_files(config.environment_files(command))
+            .log_all(options.log);
+
+        // Show what would run, without running it or recording it in the history
+        if options.dry_run {
+            print!("{}", runner.dry_run(&command_name, &args)?);
+            return Ok(());
+        }
+
+        if let Some(log_dir) = config.log_dir() {
+            runner = runner.with_log_dir(log_dir);
+        }
+        let logged = options.log || command.log;
+
+        let started_at = Local::now();
+        let started = Instant::now();
+        println!("Running command {}...", command_name);
+        let result = runner.run(&command_name, &args);
+
+        // Record the run, including runs that failed before they started, without letting
+        // a history problem fail the command. Secret values stay out of the history file.
+        if let Some(history) = &history {
+            let mask = |args: &[String]| -> Vec<String> {
+                args.iter()
+                    .map(|arg| runner.mask(&command_name, arg))
+                    .collect()
+            };
+            let resolved_args = runner
+                .prepare(&command_name, &args)
+                .map(|invocation| invocation.args)
+                .unwrap_or_else(|_| args.clone());
+            let outcome = result.as_ref().ok();
+
+            let entry = HistoryEntry {
+                command: name.to_string(),
+                args: mask(&args),
+                resolved_args: mask(&resolved_args),
+                config: command.source.clone(),
+                cwd: current_dir.clone(),
+                started_at: started_at.to_rfc3339(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                exit_code: outcome
+                    .and_then(|outcome| outcome.status())
+                    .and_then(|status| status.code()),
+                skipped: outcome.is_some_and(|outcome| outcome.status().is_none()),
+                error: result
+                    .as_ref()
+                    .err()
+                    .map(|e| runner.mask(&command_name, &e.to_string())),
+                profile: config.hoi.active_profile.clone(),
+            };
+            if let Err(e) = history.record(&entry) {
+                eprintln!(
+                    "Warning: failed to record the run in {}: {}",
+                    history.path().display(),
+                    e
+                );
+            }
+        }
+
+        match result? {
+            Outcome::Skipped(reason) => {
+                println!("Skipped command '{}' ({})", command_name, reason);
+            }
+            Outcome::Completed(status) if !status.success() => {
+                eprintln!(
+                    "Command '{}' failed with exit code: {:?}",
+                    command_name,
+                    status.code()
+                );
+                if logged {
+                    eprintln!("Run 'hoi logs {}' to see the full output.", command_name);
+                }
+            }
+            Outcome::Completed(_) => {}
+        }
     }
 
     Ok(())
@@ -426,102 +948,72 @@ mod tests {
     use std::env;
     use std::fs::{self};
     use std::path::PathBuf;
-    use temp_env::with_var;
     use testdir::testdir;
-    use utilities::copy_fixture;
 
     #[test]
-    fn test_custom_entrypoint() {
-        let temp_dir: PathBuf = testdir!();
-        copy_fixture(".hoi.with_entrypoint.yml", &temp_dir, ".hoi.yml");
-        let result = load_config(&temp_dir.join(".hoi.yml"));
-
-        assert!(
-            result.is_ok(),
-            "Failed to load valid config: {:?}",
-            result.err()
-        );
-
-        let hoi = result.unwrap();
-        assert_eq!(hoi.entrypoint, vec!["sh", "-c", "$@"]);
+    fn test_parse_options() {
+        let mut args = vec![
+            "--profile".to_string(),
+            "staging".to_string(),
+            "deploy".to_string(),
+            "--profile".to_string(),
+        ];
+        let options = parse_options(&mut args).unwrap();
+        assert_eq!(options.profile.as_deref(), Some("staging"));
+        assert_eq!(args, vec!["deploy", "--profile"]);
+
+        let mut args = vec!["--profile=prod".to_string()];
+        let options = parse_options(&mut args).unwrap();
+        assert_eq!(options.profile.as_deref(), Some("prod"));
+        assert!(args.is_empty());
+
+        let mut args = vec![
+            "--log".to_string(),
+            "--dry-run".to_string(),
+            "build".to_string(),
+        ];
+        let options = parse_options(&mut args).unwrap();
+        assert!(options.log);
+        assert!(options.dry_run);
+        assert_eq!(args, vec!["build"]);
+
+        let mut args = vec![
+            "--no-banner".to_string(),
+            "--color".to_string(),
+            "never".to_string(),
+        ];
+        let options = parse_options(&mut args).unwrap();
+        assert!(options.no_banner);
+        assert_eq!(options.color, Some(ColorChoice::Never));
+        assert!(args.is_empty());
+
+        let options = parse_options(&mut vec!["--color=always".to_string()]).unwrap();
+        assert_eq!(options.color, Some(ColorChoice::Always));
+        assert!(parse_options(&mut vec!["--color=rainbow".to_string()]).is_err());
+
+        assert!(parse_options(&mut vec!["--unknown".to_string()]).is_err());
+        assert!(parse_options(&mut vec!["--profile".to_string()]).is_err());
     }
 
     #[test]
-    fn test_find_config() {
-        let temp_dir: PathBuf = testdir!();
-        copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
-        let config_path = temp_dir.join(".hoi.yml");
-        env::set_current_dir(&temp_dir).unwrap();
-
-        let result = find_config_file();
-        assert!(result.is_some(), "Failed to find config file");
-
-        #[cfg(not(windows))]
-        {
-            let canonical_path = config_path.canonicalize().ok().unwrap();
-            assert_eq!(result.unwrap(), canonical_path);
-        }
-
-        #[cfg(windows)]
-        {
-            let result_path = result.unwrap();
-            // For Windows, just check that the file exists and has the right name
-            assert!(result_path.exists(), "Result path does not exist");
-            assert_eq!(
-                result_path.file_name().unwrap(),
-                config_path.file_name().unwrap()
-            );
-        }
-    }
-
-    #[test]
-    fn test_find_global_config() {
-        let temp_dir: PathBuf = testdir!();
-
-        #[cfg(not(windows))]
-        let (env_var, env_val) = ("HOME", &temp_dir);
-        #[cfg(windows)]
-        let (env_var, env_val) = ("USERPROFILE", &temp_dir);
-
-        with_var(env_var, Some(env_val.to_str().unwrap()), || {
-            let home_dir = dirs_next::home_dir().unwrap();
-            let hoi_dir = home_dir.join(".hoi");
-            fs::create_dir_all(&hoi_dir).unwrap();
-            copy_fixture(".hoi.global.yml", &hoi_dir, ".hoi.global.yml");
-
-            let result = find_global_config_file();
-            assert!(result.is_some(), "Failed to find global config file");
-
-            let result_path = result.unwrap();
-
-            // Platform-specific path comparison
-            #[cfg(not(windows))]
-            {
-                let canonical_path = hoi_dir.join(".hoi.global.yml").canonicalize().unwrap();
-                assert_eq!(result_path, canonical_path);
-            }
-
-            #[cfg(windows)]
-            {
-                // For Windows, just check that the file exists and has the right name
-                assert!(result_path.exists(), "Result path does not exist");
-                assert_eq!(
-                    result_path.file_name().unwrap(),
-                    hoi_dir.join(".hoi.global.yml").file_name().unwrap()
-                );
-
-                // Also verify the parent directory is correct
-                assert_eq!(
-                    result_path.parent().unwrap().file_name().unwrap(),
-                    hoi_dir
-                        .join(".hoi.global.yml")
-                        .parent()
-                        .unwrap()
-                        .file_name()
-                        .unwrap()
-                );
-            }
-        });
+    fn test_colorize_listing() {
+        let rendered = " Command  Alias  Source  Description \n build    b      local   Builds it   \n Ops:                                \n deploy          local   Ships it    ";
+        let colored = colorize_listing(rendered, &["build", "deploy"], &["b"]);
+        let lines: Vec<&str> = colored.lines().collect();
+
+        assert_eq!(lines[0], " Command  Alias  Source  Description ");
+        assert_eq!(
+            lines[1],
+            " \x1b[1;36mbuild\x1b[0m    \x1b[33mb\x1b[0m      local   Builds it   "
+        );
+        assert_eq!(
+            lines[2],
+            " \x1b[1mOps:\x1b[0m                                "
+        );
+        assert_eq!(
+            lines[3],
+            " \x1b[1;36mdeploy\x1b[0m          local   Ships it    "
+        );
     }
 
     #[test]
@@ -530,7 +1022,7 @@ mod tests {
         env::set_current_dir(&temp_dir).unwrap();
 
         // Run the init config function
-        let result = create_init_config();
+        let result = create_init_config(&[]);
         assert!(
             result.is_ok(),
             "Failed to create init config: {:?}",
@@ -549,7 +1041,7 @@ mod tests {
         fs::write(&config_path, modified_content).unwrap();
 
         // Run init again
-        let result = create_init_config();
+        let result = create_init_config(&[]);
         assert!(
             result.is_ok(),
             "Failed on second init run: {:?}",
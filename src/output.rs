@@ -0,0 +1,219 @@
+use std::env;
+use std::io::{self, IsTerminal, Read, Write};
+
+/// The text secret values are replaced with in command output.
+pub const MASK: &[u8] = b"***";
+
+/// Replaces secret values in a stream of output with `***`.
+///
+/// Output is processed in chunks as it arrives, so a secret that is split across
+/// two chunks is still masked. Only the bytes that could be the start of a secret
+/// are held back until the next chunk arrives; everything else is passed on right away.
+#[derive(Debug, Clone, Default)]
+pub struct Masker {
+    secrets: Vec<Vec<u8>>,
+}
+
+impl Masker {
+    /// Creates a masker for the given secret values. Empty values are ignored.
+    ///
+    /// # Arguments
+    /// * `secrets` - The values to mask
+    pub fn new<I, S>(secrets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        let mut secrets: Vec<Vec<u8>> = secrets
+            .into_iter()
+            .map(|secret| secret.as_ref().to_vec())
+            .filter(|secret| !secret.is_empty())
+            .collect();
+
+        // Prefer the longest match when one secret contains another
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup();
+
+        Self { secrets }
+    }
+
+    /// Returns true if there is nothing to mask.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    /// Masks the secrets in `pending` and returns the bytes that are safe to write.
+    ///
+    /// Bytes at the end of `pending` that could be the start of a secret are left in
+    /// `pending` unless `finished` is true.
+    ///
+    /// # Arguments
+    /// * `pending` - The output that has not been written yet
+    /// * `finished` - Whether the stream has ended
+    pub fn mask(&self, pending: &mut Vec<u8>, finished: bool) -> Vec<u8> {
+        let mut masked = Vec::with_capacity(pending.len());
+        let mut i = 0;
+
+        while i < pending.len() {
+            let rest = &pending[i..];
+
+            if let Some(secret) = self.secrets.iter().find(|s| rest.starts_with(s)) {
+                masked.extend_from_slice(MASK);
+                i += secret.len();
+            } else if !finished
+                && self
+                    .secrets
+                    .iter()
+                    .any(|s| s.len() > rest.len() && s.starts_with(rest))
+            {
+                break;
+            } else {
+                masked.push(pending[i]);
+                i += 1;
+            }
+        }
+
+        pending.drain(..i);
+        masked
+    }
+}
+
+/// Returns true if hoi's output is a terminal that colors may be used on: stdout is a
+/// terminal and NO_COLOR is not set. As in the NO_COLOR convention, an empty NO_COLOR counts
+/// as not set.
+pub fn colors_enabled() -> bool {
+    io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Copies a child process stream to a writer as output arrives, masking secrets on the way.
+///
+/// # Arguments
+/// * `reader` - The stream to read from, usually the stdout or stderr of a child process
+/// * `writer` - Where to write the masked output
+/// * `masker` - The masker with the secret values to replace
+pub fn pump(mut reader: impl Read, mut writer: impl Write, masker: &Masker) -> io::Result<()> {
+    let mut buffer = [0u8; 8192];
+    let mut pending = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        pending.extend_from_slice(&buffer[..read]);
+        writer.write_all(&masker.mask(&mut pending, false))?;
+        writer.flush()?;
+    }
+
+    writer.write_all(&masker.mask(&mut pending, true))?;
+    writer.flush()
+}
+
+/// Starts every line written through it with a prefix, so the output of commands that run
+/// at the same time can be told apart.
+///
+/// Only complete lines are passed on, each in a single write, so lines from different
+/// writers don't get mixed up. A last line without a newline is passed on when it is dropped.
+pub struct LinePrefix<W: Write> {
+    inner: W,
+    prefix: Vec<u8>,
+    line: Vec<u8>,
+}
+
+impl<W: Write> LinePrefix<W> {
+    /// Creates a writer that starts every line written to `inner` with `prefix`.
+    pub fn new(inner: W, prefix: &str) -> Self {
+        Self {
+            inner,
+            prefix: prefix.as_bytes().to_vec(),
+            line: Vec::new(),
+        }
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        let mut line = self.prefix.clone();
+        line.append(&mut self.line);
+        self.inner.write_all(&line)
+    }
+}
+
+impl<W: Write> Write for LinePrefix<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.line.push(byte);
+            if byte == b'\n' {
+                self.write_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for LinePrefix<W> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            self.line.push(b'\n');
+            let _ = self.write_line();
+        }
+        let _ = self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_across_chunks() {
+        let masker = Masker::new(["hunter2", "s3cr3t-token", ""]);
+        let mut pending = b"password=hun".to_vec();
+
+        assert_eq!(masker.mask(&mut pending, false), b"password=");
+        assert_eq!(pending, b"hun");
+
+        pending.extend_from_slice(b"ter2 token=s3cr3t-token\n");
+        assert_eq!(masker.mask(&mut pending, false), b"*** token=***\n");
+        assert!(pending.is_empty());
+
+        pending.extend_from_slice(b"almost s3cr3t");
+        assert_eq!(masker.mask(&mut pending, false), b"almost ");
+        assert_eq!(masker.mask(&mut pending, true), b"s3cr3t");
+    }
+
+    #[test]
+    fn test_pump() {
+        let masker = Masker::new(["hunter2"]);
+        let mut output = Vec::new();
+
+        pump(
+            &b"\x1b[32mpassword: hunter2\x1b[0m\n"[..],
+            &mut output,
+            &masker,
+        )
+        .unwrap();
+        assert_eq!(output, b"\x1b[32mpassword: ***\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_line_prefix() {
+        let mut output = Vec::new();
+        {
+            let mut writer = LinePrefix::new(&mut output, "[a] ");
+            writer.write_all(b"one\ntw").unwrap();
+            writer.flush().unwrap();
+            writer.write_all(b"o\nthree").unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[a] one\n[a] two\n[a] three\n"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::hoi::{Hoi, HoiError};
+use crate::user_command::{trimmed, Cmd, UserCommand};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Personal overrides loaded from a .hoi.local.yml file next to a .hoi.yml file.
+///
+/// The file is meant to be kept out of version control. It can add new commands,
+/// change parts of the commands defined in the shared .hoi.yml, or disable them.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LocalOverrides {
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub entrypoint: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub commands: IndexMap<String, CommandOverride>,
+}
+
+/// A partial command definition that is layered on top of an existing command.
+///
+/// Every field is optional; only the fields that are set replace the existing ones,
+/// and `env` entries are merged into the existing environment variables.
+#[derive(Deserialize, Debug, Default, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CommandOverride {
+    /// Replaces the command to run.
+    #[serde(default)]
+    pub cmd: Option<Cmd>,
+
+    /// Replaces the alias of the command.
+    #[serde(default, deserialize_with = "trimmed")]
+    pub alias: Option<String>,
+
+    /// Replaces the description of the command.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Environment variables added to, or replacing, those of the command.
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+
+    /// Replaces the entrypoint of the command.
+    #[serde(default)]
+    pub entrypoint: Option<Vec<String>>,
+
+    /// Removes the command entirely.
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+impl LocalOverrides {
+    /// Applies the overrides to a merged configuration.
+    ///
+    /// # Arguments
+    /// * `hoi` - The configuration to apply the overrides to
+    /// * `path` - The path of the overrides file, recorded as the source of new commands
+    ///
+    /// # Errors
+    /// * `HoiError::InvalidOverride` - If a new command is added without a `cmd`
+    pub fn apply(self, hoi: &mut Hoi, path: &Path) -> Result<(), HoiError> {
+        if let Some(entrypoint) = self.entrypoint {
+            hoi.entrypoint = entrypoint;
+        }
+
+        if let Some(description) = self.description {
+            hoi.description = description;
+        }
+
+        for (name, command_override) in self.commands {
+            command_override.apply(&mut hoi.commands, &name, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CommandOverride {
+    /// Applies the override to the command called `name`, adding it if it does not exist yet.
+    ///
+    /// # Arguments
+    /// * `commands` - The commands to update
+    /// * `name` - The name of the command to override
+    /// * `path` - The path of the overrides file, recorded as the source of a new command
+    ///
+    /// # Errors
+    /// * `HoiError::InvalidOverride` - If the command does not exist and no `cmd` is given
+    pub fn apply(
+        self,
+        commands: &mut IndexMap<String, UserCommand>,
+        name: &str,
+        path: &Path,
+    ) -> Result<(), HoiError> {
+        if self.disabled {
+            commands.shift_remove(name);
+            return Ok(());
+        }
+
+        let command = match commands.get_mut(name) {
+            Some(command) => command,
+            None => {
+                let cmd = self.cmd.clone().ok_or_else(|| {
+                    HoiError::InvalidOverride(format!(
+                        "Command '{}' in {} is not defined elsewhere and has no cmd.",
+                        name,
+                        path.display()
+                    ))
+                })?;
+
+                commands.entry(name.to_string()).or_insert(UserCommand {
+                    cmd,
+                    source: Some(path.to_path_buf()),
+                    ..UserCommand::default()
+                })
+            }
+        };
+
+        if let Some(cmd) = self.cmd {
+            command.cmd = cmd;
+        }
+
+        if self.alias.is_some() {
+            command.alias = self.alias;
+        }
+
+        if let Some(description) = self.description {
+            command.description = description;
+        }
+
+        if self.entrypoint.is_some() {
+            command.entrypoint = self.entrypoint;
+        }
+
+        command.env.extend(self.env);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use crate::environment::Dotenv;
+use crate::hoi::{Hoi, HoiError};
+use crate::overrides::CommandOverride;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::Path;
+
+/// A named set of overrides, such as `dev`, `staging` or `prod`, selected with
+/// `hoi --profile <name>` or the `HOI_PROFILE` environment variable.
+#[derive(Deserialize, Debug, Default, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Profile {
+    /// A description of the profile.
+    #[serde(default)]
+    pub description: String,
+
+    /// Variables that override the top-level `vars` while the profile is active.
+    #[serde(default)]
+    pub vars: IndexMap<String, String>,
+
+    /// Environment variables that override the top-level `env` while the profile is active.
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+
+    /// The dotenv files to load instead of the top-level `dotenv` setting.
+    #[serde(default)]
+    pub dotenv: Option<Dotenv>,
+
+    /// Changes to individual commands while the profile is active, keyed by command name.
+    #[serde(default)]
+    pub commands: IndexMap<String, CommandOverride>,
+}
+
+impl Hoi {
+    /// Activates a profile, applying its overrides on top of the merged configuration.
+    ///
+    /// When the configuration defines no profiles at all, the name is still recorded as the
+    /// active profile so commands restricted to certain profiles can check it.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the profile to activate
+    /// * `path` - The path recorded as the source of commands the profile adds
+    ///
+    /// # Errors
+    /// * `HoiError::ProfileNotFound` - If profiles are defined but none has this name
+    /// * `HoiError::InvalidOverride` - If the profile adds a command without a `cmd`
+    pub fn apply_profile(&mut self, name: &str, path: &Path) -> Result<(), HoiError> {
+        if self.profiles.is_empty() {
+            self.active_profile = Some(name.to_string());
+            return Ok(());
+        }
+
+        // A profile that isn't found leaves the configuration as it was
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            HoiError::ProfileNotFound(
+                name.to_string(),
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", "),
+            )
+        })?;
+        self.active_profile = Some(name.to_string());
+
+        self.vars.extend(profile.vars);
+        self.env.extend(profile.env);
+
+        if profile.dotenv.is_some() {
+            self.dotenv = profile.dotenv;
+        }
+
+        for (command_name, command_override) in profile.commands {
+            command_override.apply(&mut self.commands, &command_name, path)?;
+        }
+
+        Ok(())
+    }
+}
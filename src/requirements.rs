@@ -0,0 +1,328 @@
+use std::cmp::Ordering;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A tool a command needs, with an optional version constraint, as in `"node >= 18"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// The name or path of the executable.
+    pub tool: String,
+
+    /// The version constraints, all of which must hold. Empty means any version.
+    pub constraints: Vec<(Operator, Vec<u64>)>,
+}
+
+/// A comparison operator in a version constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `>=`
+    AtLeast,
+    /// `>`
+    Above,
+    /// `<=`
+    AtMost,
+    /// `<`
+    Below,
+    /// `=` or `==`
+    Exactly,
+}
+
+impl Operator {
+    /// Returns true if the ordering of a version compared to the constraint satisfies it.
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Operator::AtLeast => ordering != Ordering::Less,
+            Operator::Above => ordering == Ordering::Greater,
+            Operator::AtMost => ordering != Ordering::Greater,
+            Operator::Below => ordering == Ordering::Less,
+            Operator::Exactly => ordering == Ordering::Equal,
+        }
+    }
+}
+
+impl Requirement {
+    /// Parses a requirement: the name of a tool, optionally followed by version constraints
+    /// separated by commas, as in `"node >= 18"` or `"python3 >= 3.10, < 4"`.
+    ///
+    /// # Arguments
+    /// * `requirement` - The requirement to parse
+    ///
+    /// # Returns
+    /// * `Option<Requirement>` - The requirement, or None if it is not valid
+    pub fn parse(requirement: &str) -> Option<Requirement> {
+        let split = requirement
+            .find(['<', '>', '='])
+            .unwrap_or(requirement.len());
+        let tool = requirement[..split].trim();
+        if tool.is_empty() || tool.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let mut constraints = Vec::new();
+        let rest = requirement[split..].trim();
+        if !rest.is_empty() {
+            for constraint in rest.split(',') {
+                constraints.push(parse_constraint(constraint.trim())?);
+            }
+        }
+
+        Some(Requirement {
+            tool: tool.to_string(),
+            constraints,
+        })
+    }
+}
+
+/// Parses a single version constraint, such as `>= 18` or `< 4.1`.
+fn parse_constraint(constraint: &str) -> Option<(Operator, Vec<u64>)> {
+    let operators = [
+        (">=", Operator::AtLeast),
+        ("<=", Operator::AtMost),
+        ("==", Operator::Exactly),
+        (">", Operator::Above),
+        ("<", Operator::Below),
+        ("=", Operator::Exactly),
+    ];
+
+    let (operator, version) = operators.iter().find_map(|(prefix, operator)| {
+        constraint
+            .strip_prefix(prefix)
+            .map(|version| (*operator, version.trim()))
+    })?;
+
+    let version = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    Some((operator, version))
+}
+
+/// Returns the first version number in a text, such as `18.19.0` in `v18.19.0`.
+///
+/// # Arguments
+/// * `text` - The text to search, usually the output of `<tool> --version`
+pub fn find_version(text: &str) -> Option<Vec<u64>> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let version: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+
+    version
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Compares two versions component by component, treating missing components as 0.
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    let length = a.len().max(b.len());
+    (0..length)
+        .map(|i| {
+            let a = a.get(i).copied().unwrap_or(0);
+            let b = b.get(i).copied().unwrap_or(0);
+            a.cmp(&b)
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Checks that the required tools are installed, in the directories of the PATH
+/// environment variable.
+///
+/// # Arguments
+/// * `requirements` - The requirements, as written in the configuration
+///
+/// # Returns
+/// * `Vec<String>` - A description of each requirement that isn't met
+pub fn check(requirements: &[String]) -> Vec<String> {
+    let path = env::var_os("PATH").unwrap_or_default();
+    check_with_path(requirements, &path)
+}
+
+/// Checks that the required tools are installed, in the directories of the given search path.
+///
+/// Tools are looked up as executables in the search path, or as paths when they contain a
+/// path separator. Tools with version constraints are run with `--version`, and the first
+/// version number they print is compared to the constraints.
+///
+/// # Arguments
+/// * `requirements` - The requirements, as written in the configuration
+/// * `path` - The directories to search, in the format of the PATH environment variable
+///
+/// # Returns
+/// * `Vec<String>` - A description of each requirement that isn't met
+pub fn check_with_path(requirements: &[String], path: &OsStr) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for written in requirements {
+        let Some(requirement) = Requirement::parse(written) else {
+            problems.push(format!(
+                "{}: not a valid requirement, write it as a tool name with an optional version, such as \"node >= 18\"",
+                written
+            ));
+            continue;
+        };
+
+        let Some(executable) = find_executable(&requirement.tool, path) else {
+            problems.push(format!("{}: not found on PATH", written));
+            continue;
+        };
+
+        if requirement.constraints.is_empty() {
+            continue;
+        }
+
+        let version = Command::new(&executable)
+            .arg("--version")
+            .output()
+            .ok()
+            .and_then(|output| {
+                find_version(&String::from_utf8_lossy(&output.stdout))
+                    .or_else(|| find_version(&String::from_utf8_lossy(&output.stderr)))
+            });
+        let Some(version) = version else {
+            problems.push(format!(
+                "{}: could not determine the version from `{} --version`",
+                written, requirement.tool
+            ));
+            continue;
+        };
+
+        let satisfied = requirement
+            .constraints
+            .iter()
+            .all(|(operator, wanted)| operator.accepts(compare_versions(&version, wanted)));
+        if !satisfied {
+            let found: Vec<String> = version.iter().map(u64::to_string).collect();
+            problems.push(format!("{}: found version {}", written, found.join(".")));
+        }
+    }
+
+    problems
+}
+
+/// Finds an executable by name in a search path, or checks it exists when given as a path.
+fn find_executable(tool: &str, path: &OsStr) -> Option<PathBuf> {
+    if tool.contains(['/', '\\']) {
+        let tool = PathBuf::from(tool);
+        return is_executable(&tool).then_some(tool);
+    }
+
+    env::split_paths(path).find_map(|dir| {
+        executable_names(tool)
+            .into_iter()
+            .map(|name| dir.join(name))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+/// Returns the file names an executable can have: on Windows, with each extension
+/// of the PATHEXT environment variable.
+fn executable_names(tool: &str) -> Vec<String> {
+    #[cfg(windows)]
+    {
+        let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+        std::iter::once(tool.to_string())
+            .chain(extensions.split(';').map(|ext| format!("{}{}", tool, ext)))
+            .collect()
+    }
+
+    #[cfg(not(windows))]
+    {
+        vec![tool.to_string()]
+    }
+}
+
+/// Returns true if a path is a file that can be executed.
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirement() {
+        assert_eq!(
+            Requirement::parse("docker"),
+            Some(Requirement {
+                tool: "docker".to_string(),
+                constraints: Vec::new(),
+            })
+        );
+        assert_eq!(
+            Requirement::parse("python3>=3.10, < 4"),
+            Some(Requirement {
+                tool: "python3".to_string(),
+                constraints: vec![(Operator::AtLeast, vec![3, 10]), (Operator::Below, vec![4])],
+            })
+        );
+        assert_eq!(Requirement::parse("node >= eighteen"), None);
+        assert_eq!(Requirement::parse(">= 18"), None);
+        assert_eq!(Requirement::parse("node js"), None);
+    }
+
+    #[test]
+    fn test_find_and_compare_versions() {
+        assert_eq!(find_version("v18.19.0"), Some(vec![18, 19, 0]));
+        assert_eq!(
+            find_version("cargo 1.82.0 (8f40fc59f 2024-08-21)"),
+            Some(vec![1, 82, 0])
+        );
+        assert_eq!(find_version("no version here"), None);
+
+        assert_eq!(compare_versions(&[18], &[18, 0, 0]), Ordering::Equal);
+        assert_eq!(compare_versions(&[1, 10], &[1, 9, 5]), Ordering::Greater);
+        assert_eq!(compare_versions(&[3, 9], &[3, 10]), Ordering::Less);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_with_path() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        use testdir::testdir;
+
+        let temp_dir = testdir!();
+        let tool = temp_dir.join("fake-node");
+        fs::write(&tool, "#!/bin/sh\necho v16.20.1\n").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(temp_dir.join("not-executable"), "").unwrap();
+
+        let requirements: Vec<String> = [
+            "fake-node",
+            "fake-node >= 16, < 17",
+            "fake-node >= 18",
+            "not-executable",
+            "missing-tool",
+        ]
+        .iter()
+        .map(|r| r.to_string())
+        .collect();
+
+        assert_eq!(
+            check_with_path(&requirements, temp_dir.as_os_str()),
+            vec![
+                "fake-node >= 18: found version 16.20.1",
+                "not-executable: not found on PATH",
+                "missing-tool: not found on PATH",
+            ]
+        );
+    }
+}
@@ -0,0 +1,1716 @@
+use crate::condition::Condition;
+use crate::environment::check_required_vars;
+use crate::hoi::{Hoi, HoiError};
+use crate::logs::{apply_retention, LogFile, Tee};
+use crate::output::{colors_enabled, pump, LinePrefix, Masker};
+use crate::requirements;
+use crate::template;
+use crate::user_command::{Cmd, Step, UserCommand};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use tabled::builder::Builder;
+use tabled::settings::object::Columns;
+use tabled::settings::{Alignment, Modify, Padding, Style};
+
+/// The environment variable set to the name of the running command for its process, so
+/// nested hoi calls can tell they were started by another command.
+pub const PARENT_COMMAND_VAR: &str = "HOI_PARENT_COMMAND";
+
+/// Executes commands defined in a Hoi configuration.
+///
+/// The runner only prints the headers of steps and which step failed; callers decide
+/// how to report the returned exit status.
+pub struct Runner<'a> {
+    hoi: &'a Hoi,
+    secrets: Vec<String>,
+    /// The dotenv files that would usually define required variables, to name when some are missing.
+    environment_files: Vec<PathBuf>,
+    log_dir: Option<PathBuf>,
+    log_all: bool,
+    /// The values of the matrix combination being run, and the command it belongs to.
+    matrix: IndexMap<String, String>,
+    matrix_of: Option<String>,
+    /// Starts every line of output, when combinations of a matrix run at the same time.
+    output_prefix: Option<String>,
+}
+
+impl<'a> Runner<'a> {
+    /// Creates a runner for the given configuration.
+    ///
+    /// # Arguments
+    /// * `hoi` - The Hoi configuration struct containing command definitions
+    pub fn new(hoi: &'a Hoi) -> Self {
+        Self {
+            hoi,
+            secrets: Vec::new(),
+            environment_files: Vec::new(),
+            log_dir: None,
+            log_all: false,
+            matrix: IndexMap::new(),
+            matrix_of: None,
+            output_prefix: None,
+        }
+    }
+
+    /// Sets the directory command logs are written to. Defaults to `.hoi/logs` in the
+    /// current directory.
+    ///
+    /// # Arguments
+    /// * `log_dir` - The directory to write logs to, one subdirectory per command
+    pub fn with_log_dir(mut self, log_dir: PathBuf) -> Self {
+        self.log_dir = Some(log_dir);
+        self
+    }
+
+    /// Writes a log for every command that runs, not only for commands with `log: true`.
+    ///
+    /// # Arguments
+    /// * `log_all` - Whether to log every command
+    pub fn log_all(mut self, log_all: bool) -> Self {
+        self.log_all = log_all;
+        self
+    }
+
+    /// Marks more variables as secret, in addition to the configured `secret` list.
+    ///
+    /// # Arguments
+    /// * `names` - The names of the variables whose values should be masked
+    pub fn with_secrets(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.secrets.extend(names);
+        self
+    }
+
+    /// Sets the dotenv files that were loaded for the command, which errors about missing
+    /// required variables suggest defining them in.
+    ///
+    /// # Arguments
+    /// * `files` - The dotenv files, in the order they were loaded
+    pub fn with_environment_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.environment_files = files;
+        self
+    }
+
+    /// Prepares a command defined in the Hoi configuration to run, without running it.
+    ///
+    /// The command runs with its `prepend_args`, followed by the given arguments or its
+    /// `default_args` when none are given. These are filled into the placeholders of its
+    /// script, or of each of its steps, or passed on as set by `pass_args`, and the result is
+    /// put into its entrypoint, handling special placeholder substitution ($@).
+    ///
+    /// # Arguments
+    /// * `command_name` - The name or alias of the command to prepare
+    /// * `args` - Additional arguments to pass to the command
+    ///
+    /// # Errors
+    /// * `HoiError::CommandNotFound` - If the command, or a command one of its steps runs, is
+    ///   not defined in the configuration
+    /// * `HoiError::ProfileNotAllowed` - If the command can't run with the active profile
+    /// * `HoiError::MissingArgument` - If a required argument is not given
+    /// * `HoiError::InvalidStep` - If a step has both or neither of `cmd` and `run`
+    /// * `HoiError::InvalidMatrix` - If a matrix variable has no values or an invalid name
+    pub fn prepare(&self, command_name: &str, args: &[String]) -> Result<Invocation, HoiError> {
+        let (name, command) = self
+            .hoi
+            .find_command(command_name)
+            .ok_or_else(|| HoiError::CommandNotFound(command_name.to_string()))?;
+
+        // Refuse to run commands restricted to other profiles
+        if !self.hoi.can_run(command) {
+            return Err(HoiError::ProfileNotAllowed {
+                command: name.to_string(),
+                allowed: command.profiles.clone(),
+            });
+        }
+
+        if let Some(message) = command.matrix_problem() {
+            return Err(HoiError::InvalidMatrix {
+                command: name.to_string(),
+                message,
+            });
+        }
+
+        let args = command.effective_args(args);
+        if let Some(argument) = command.missing_argument(&args) {
+            return Err(HoiError::MissingArgument {
+                command: name.to_string(),
+                argument: argument.to_string(),
+            });
+        }
+
+        let steps = match &command.cmd {
+            Cmd::Script(script) => vec![PreparedStep {
+                title: None,
+                action: StepAction::Process(self.hoi.command_line(
+                    name,
+                    command,
+                    script,
+                    &self.vars(),
+                    &args,
+                )),
+                dir: None,
+                env: IndexMap::new(),
+                r#if: None,
+                unless: None,
+                ignore_errors: false,
+            }],
+            Cmd::Steps(steps) => steps
+                .iter()
+                .enumerate()
+                .map(|(index, step)| self.prepare_step(name, command, index, step, &args))
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(Invocation {
+            name: name.to_string(),
+            args,
+            steps,
+        })
+    }
+
+    /// Prepares a step of a command.
+    fn prepare_step(
+        &self,
+        name: &str,
+        command: &UserCommand,
+        index: usize,
+        step: &Step,
+        args: &[String],
+    ) -> Result<PreparedStep, HoiError> {
+        let title = step.title();
+        let step = step.definition();
+        let invalid = |message: &str| HoiError::InvalidStep {
+            command: name.to_string(),
+            step: index + 1,
+            message: message.to_string(),
+        };
+
+        let action = match (step.cmd, step.run) {
+            (Some(_), Some(_)) | (None, None) => {
+                return Err(invalid("needs either a cmd or a run, but not both"));
+            }
+            (Some(_), None) if !step.args.is_empty() => {
+                return Err(invalid("can only have args when it runs another command"));
+            }
+            (Some(script), None) => StepAction::Process(self.hoi.command_line(
+                name,
+                command,
+                &script,
+                &self.vars(),
+                args,
+            )),
+            (None, Some(run)) => {
+                if self.hoi.find_command(&run).is_none() {
+                    return Err(HoiError::CommandNotFound(run));
+                }
+                StepAction::Command {
+                    name: run,
+                    args: step.args,
+                }
+            }
+        };
+
+        // Directories are relative to the configuration file that defines the command
+        let dir = step.dir.map(|dir| match command.dir() {
+            Some(base) => base.join(dir),
+            None => dir,
+        });
+
+        Ok(PreparedStep {
+            title: Some(title),
+            action,
+            dir,
+            env: step.env,
+            r#if: step.r#if,
+            unless: step.unless,
+            ignore_errors: step.ignore_errors,
+        })
+    }
+
+    /// Describes what running a command would do, without running it: the arguments it would
+    /// run with and the command line of each of its steps, with secret values masked.
+    ///
+    /// # Arguments
+    /// * `command_name` - The name or alias of the command
+    /// * `args` - Additional arguments to pass to the command
+    ///
+    /// # Errors
+    /// * See [`Runner::prepare`]
+    pub fn dry_run(&self, command_name: &str, args: &[String]) -> Result<String, HoiError> {
+        let invocation = self.prepare(command_name, args)?;
+        let command = &self.hoi.commands[&invocation.name];
+        let total = invocation.steps.len();
+
+        let mut description = format!("{}\n", invocation.describe());
+        description.push_str(&condition_notes(&command.r#if, &command.unless));
+        if self.expands_matrix(&invocation.name, command) {
+            let combinations = command.matrix_combinations();
+            description.push_str(&format!(
+                "# matrix, {} combinations{}:\n",
+                combinations.len(),
+                if command.parallel { " in parallel" } else { "" }
+            ));
+            for combination in &combinations {
+                description.push_str(&format!("#   {}\n", matrix_label(combination)));
+            }
+        }
+        for (index, step) in invocation.steps.iter().enumerate() {
+            if let Some(title) = &step.title {
+                description.push_str(&format!("==> [{}/{}] {}\n", index + 1, total, title));
+            }
+            description.push_str(&condition_notes(&step.r#if, &step.unless));
+
+            let mut line = String::new();
+            if let Some(dir) = &step.dir {
+                line.push_str(&format!(
+                    "cd {} && ",
+                    template::shell_quote(&dir.to_string_lossy())
+                ));
+            }
+            for (key, value) in &step.env {
+                line.push_str(&format!("{}={} ", key, template::shell_quote(value)));
+            }
+            match &step.action {
+                StepAction::Process(command_line) => {
+                    line.push_str(&template::quote_words(command_line))
+                }
+                StepAction::Command { name, args } => {
+                    let mut words = vec!["hoi".to_string(), name.clone()];
+                    words.extend(args.iter().cloned());
+                    line.push_str(&template::quote_words(&words));
+                }
+            }
+            if step.ignore_errors {
+                line.push_str(" || true");
+            }
+            description.push_str(&line);
+            description.push('\n');
+        }
+
+        let masker = Masker::new(self.secret_values(command));
+        let masked = masker.mask(&mut description.into_bytes(), true);
+
+        Ok(String::from_utf8_lossy(&masked).into_owned())
+    }
+
+    /// Executes a command defined in the Hoi configuration.
+    ///
+    /// The command is prepared as described in [`Runner::prepare`] and run with the
+    /// environment of the configuration and the command. Commands with steps print a header
+    /// before each step and stop at the first step that fails, unless it ignores errors.
+    ///
+    /// Commands and steps whose `if` condition doesn't hold, or whose `unless` condition
+    /// does, are skipped. The steps after a skipped step or command still run.
+    ///
+    /// Commands with a matrix run once for every combination of its values, and print a grid
+    /// of the results at the end. Their conditions are checked for each combination.
+    ///
+    /// # Arguments
+    /// * `command_name` - The name or alias of the command to execute
+    /// * `args` - Additional arguments to pass to the command
+    ///
+    /// # Returns
+    /// * `Result<Outcome, HoiError>` - The exit status of the command, or of the step that
+    ///   failed, whether it was skipped, or an error
+    ///
+    /// # Errors
+    /// * `HoiError::CommandNotFound` - If the specified command is not defined in the configuration
+    /// * `HoiError::ProfileNotAllowed` - If the command can't run with the active profile
+    /// * `HoiError::MissingArgument` - If a required argument is not given
+    /// * `HoiError::InvalidStep` - If a step has both or neither of `cmd` and `run`
+    /// * `HoiError::CommandLoop` - If steps run a command that is already running
+    /// * `HoiError::InvalidMatrix` - If a matrix variable has no values or an invalid name
+    /// * `HoiError::RequirementsNotMet` - If a required tool is missing or out of date
+    /// * `HoiError::EnvironmentNotSet` - If a command that runs needs environment variables
+    ///   that are missing or invalid
+    /// * `HoiError::Io` - If there's an IO error executing the command
+    pub fn run(&self, command_name: &str, args: &[String]) -> Result<Outcome, HoiError> {
+        let invocation = self.prepare(command_name, args)?;
+        let command = &self.hoi.commands[&invocation.name];
+        let matrix = self.expands_matrix(&invocation.name, command);
+
+        // A skipped command doesn't need its tools, so check its conditions first
+        if !matrix {
+            if let Some(reason) = self.skip_reason(&invocation, command, None)? {
+                return Ok(Outcome::Skipped(reason));
+            }
+        }
+
+        // Check every required tool before anything runs, so a command doesn't fail halfway
+        let problems = requirements::check(&self.requirements(&invocation.name));
+        if !problems.is_empty() {
+            return Err(HoiError::RequirementsNotMet {
+                command: invocation.name,
+                problems,
+            });
+        }
+
+        if matrix {
+            return self.run_matrix(&invocation, command, args, &[], None);
+        }
+        self.run_invocation(&invocation, command, &mut Vec::new(), None)
+            .map(Outcome::Completed)
+    }
+
+    /// Returns the tools a command needs: those of the configuration, of the command, and of
+    /// the commands its steps run.
+    ///
+    /// # Arguments
+    /// * `command_name` - The name or alias of the command
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The requirements, without duplicates
+    pub fn requirements(&self, command_name: &str) -> Vec<String> {
+        let mut requirements = self.hoi.requires.clone();
+
+        for (_, command) in self.hoi.command_chain(command_name) {
+            for requirement in &command.requires {
+                if !requirements.contains(requirement) {
+                    requirements.push(requirement.clone());
+                }
+            }
+        }
+
+        requirements
+    }
+
+    /// Runs a command from the command line or from a step of another command.
+    ///
+    /// # Arguments
+    /// * `command_name` - The name or alias of the command to execute
+    /// * `args` - Additional arguments to pass to the command
+    /// * `running` - The commands that are running the steps that led to this one
+    /// * `parent_log` - The log of the command whose step runs this one, if it is logged
+    fn run_nested(
+        &self,
+        command_name: &str,
+        args: &[String],
+        running: &mut Vec<String>,
+        parent_log: Option<&LogFile>,
+    ) -> Result<Outcome, HoiError> {
+        let invocation = self.prepare(command_name, args)?;
+        let command = &self.hoi.commands[&invocation.name];
+
+        if self.expands_matrix(&invocation.name, command) {
+            return self.run_matrix(&invocation, command, args, running, parent_log);
+        }
+
+        if let Some(reason) = self.skip_reason(&invocation, command, None)? {
+            return Ok(Outcome::Skipped(reason));
+        }
+
+        self.run_invocation(&invocation, command, running, parent_log)
+            .map(Outcome::Completed)
+    }
+
+    /// Returns true if running a command runs each combination of its matrix, which it does
+    /// unless it has no matrix or this runner already runs one of its combinations.
+    fn expands_matrix(&self, name: &str, command: &UserCommand) -> bool {
+        !command.matrix.is_empty() && self.matrix_of.as_deref() != Some(name)
+    }
+
+    /// Runs a command once for every combination of its matrix, one after another or at the
+    /// same time, and prints a grid with the result of each combination.
+    ///
+    /// Every combination runs, even after one fails. The outcome is that of the first
+    /// combination that failed, or a success unless every combination was skipped.
+    ///
+    /// # Arguments
+    /// * `invocation` - The prepared command
+    /// * `command` - The definition of the command
+    /// * `args` - The arguments given for the command, to prepare each combination with
+    /// * `running` - The commands that are running the steps that led to this one
+    /// * `parent_log` - The log of the command whose step runs this one, if it is logged
+    fn run_matrix(
+        &self,
+        invocation: &Invocation,
+        command: &UserCommand,
+        args: &[String],
+        running: &[String],
+        parent_log: Option<&LogFile>,
+    ) -> Result<Outcome, HoiError> {
+        let name = invocation.name.as_str();
+        let combinations = command.matrix_combinations();
+        let total = combinations.len();
+        let masker = Masker::new(self.secret_values(command));
+
+        let own_log = self.open_log(invocation, command, &masker, parent_log)?;
+        let log = parent_log.or(own_log.as_ref());
+
+        // Each combination runs with a runner that knows its values
+        let run_combination = |combination: &IndexMap<String, String>, output_prefix| {
+            let mut matrix = self.matrix.clone();
+            matrix.extend(combination.clone());
+            let runner = Runner {
+                hoi: self.hoi,
+                secrets: self.secrets.clone(),
+                environment_files: self.environment_files.clone(),
+                log_dir: self.log_dir.clone(),
+                log_all: self.log_all,
+                matrix,
+                matrix_of: Some(name.to_string()),
+                output_prefix,
+            };
+            runner.run_nested(name, args, &mut running.to_vec(), log)
+        };
+
+        let outcomes = if command.parallel {
+            thread::scope(|scope| {
+                let handles: Vec<_> = combinations
+                    .iter()
+                    .map(|combination| {
+                        let prefix = format!("[{}] ", matrix_label(combination));
+                        let prefix = match &self.output_prefix {
+                            Some(outer) => format!("{}{}", outer, prefix),
+                            None => prefix,
+                        };
+                        scope.spawn(|| run_combination(combination, Some(prefix)))
+                    })
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("matrix thread panicked"))
+                    .collect::<Result<Vec<_>, _>>()
+            })?
+        } else {
+            let mut outcomes = Vec::new();
+            for (index, combination) in combinations.iter().enumerate() {
+                let header = format!(
+                    "==> [matrix {}/{}] {}",
+                    index + 1,
+                    total,
+                    matrix_label(combination)
+                );
+                self.report(&header, false, &masker, log)?;
+                outcomes.push(run_combination(combination, self.output_prefix.clone())?);
+            }
+            outcomes
+        };
+
+        // Show the result of every combination in a grid
+        let mut builder = Builder::default();
+        builder.push_record(
+            command
+                .matrix
+                .keys()
+                .cloned()
+                .chain(std::iter::once("Result".to_string())),
+        );
+        for (combination, outcome) in combinations.iter().zip(&outcomes) {
+            let result = match outcome {
+                Outcome::Completed(status) if status.success() => "ok".to_string(),
+                Outcome::Completed(status) => match status.code() {
+                    Some(code) => format!("failed with exit code {}", code),
+                    None => "failed by a signal".to_string(),
+                },
+                Outcome::Skipped(reason) => format!("skipped ({})", reason),
+            };
+            builder.push_record(
+                combination
+                    .values()
+                    .map(|value| matrix_value(value))
+                    .chain(std::iter::once(result)),
+            );
+        }
+        let mut grid = builder.build();
+        grid.with(Style::blank())
+            .with(Padding::new(1, 1, 0, 0))
+            .with(Modify::new(Columns::new(..)).with(Alignment::left()));
+        self.report(
+            &format!("\nResults of {}:\n{}", name, grid),
+            false,
+            &masker,
+            log,
+        )?;
+
+        let outcome = match outcomes.iter().find(|outcome| outcome.failed()) {
+            Some(failed) => failed.clone(),
+            None if outcomes.iter().all(|outcome| outcome.status().is_none()) => {
+                Outcome::Skipped("every combination of the matrix was skipped".to_string())
+            }
+            None => Outcome::Completed(ExitStatus::default()),
+        };
+
+        if let Some(log) = &own_log {
+            let code = outcome.status().and_then(|status| status.code());
+            self.close_log(log, name, code)?;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Runs a prepared command that isn't skipped, writing its log if it is logged.
+    ///
+    /// # Arguments
+    /// * `invocation` - The prepared command
+    /// * `command` - The definition of the command
+    /// * `running` - The commands that are running the steps that led to this one
+    /// * `parent_log` - The log of the command whose step runs this one, if it is logged
+    fn run_invocation(
+        &self,
+        invocation: &Invocation,
+        command: &UserCommand,
+        running: &mut Vec<String>,
+        parent_log: Option<&LogFile>,
+    ) -> Result<ExitStatus, HoiError> {
+        let name = invocation.name.as_str();
+
+        // Only commands that aren't skipped need their variables, so check them as they start
+        self.check_environment(invocation, command, running.is_empty())?;
+
+        // Steps must not run a command that is already running, which would never end
+        running.push(name.to_string());
+        if running[..running.len() - 1].contains(&invocation.name) {
+            return Err(HoiError::CommandLoop(running.join(" -> ")));
+        }
+
+        let masker = Masker::new(self.secret_values(command));
+        let own_log = self.open_log(invocation, command, &masker, parent_log)?;
+        let log = parent_log.or(own_log.as_ref());
+
+        let status = self.run_steps(invocation, command, &masker, log, running);
+        running.pop();
+        let status = status?;
+
+        if let Some(log) = &own_log {
+            self.close_log(log, name, status.code())?;
+        }
+
+        Ok(status)
+    }
+
+    /// Checks that the environment variables a command needs are set and match their
+    /// patterns, right before it runs.
+    ///
+    /// Variables can come from the process environment, including loaded dotenv files, the
+    /// values of the matrix combination being run, or the `env` of the configuration or the
+    /// command.
+    ///
+    /// # Arguments
+    /// * `invocation` - The prepared command
+    /// * `command` - The definition of the command
+    /// * `top_level` - Whether the command was run from the command line rather than by a
+    ///   step, in which case the top-level `env_required` variables are checked too
+    ///
+    /// # Errors
+    /// * `HoiError::EnvironmentNotSet` - If a variable is missing or doesn't match its pattern,
+    ///   with a hint naming the dotenv files that would usually define it
+    fn check_environment(
+        &self,
+        invocation: &Invocation,
+        command: &UserCommand,
+        top_level: bool,
+    ) -> Result<(), HoiError> {
+        let lookup = |name: &str| {
+            self.matrix
+                .get(name)
+                .or_else(|| command.env.get(name))
+                .or_else(|| self.hoi.env.get(name))
+                .cloned()
+                .or_else(|| env::var(name).ok())
+        };
+
+        let mut problems = Vec::new();
+        let required = top_level
+            .then_some(&self.hoi.env_required)
+            .into_iter()
+            .chain([&command.env_required]);
+        for required in required {
+            for problem in check_required_vars(required, lookup) {
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let files: Vec<String> = self
+            .environment_files
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect();
+        let hint = if files.is_empty() {
+            "Set them in your shell, then run the command again.".to_string()
+        } else {
+            format!(
+                "Set them in your shell or define them in {}, then run the command again.",
+                files.join(" or ")
+            )
+        };
+
+        Err(HoiError::EnvironmentNotSet {
+            command: invocation.name.clone(),
+            problems,
+            hint,
+        })
+    }
+
+    /// Opens a log file for a run of a command when it is logged. Commands run by steps
+    /// write to the log of the command they belong to, so they don't get their own.
+    fn open_log(
+        &self,
+        invocation: &Invocation,
+        command: &UserCommand,
+        masker: &Masker,
+        parent_log: Option<&LogFile>,
+    ) -> Result<Option<LogFile>, HoiError> {
+        if parent_log.is_some() || !(self.log_all || command.log) {
+            return Ok(None);
+        }
+
+        let log = LogFile::create(&self.log_dir(), &invocation.name)?;
+        let header = masker.mask(&mut invocation.describe().into_bytes(), true);
+        log.write_line(&header)?;
+        Ok(Some(log))
+    }
+
+    /// Writes the exit code at the end of a log and cleans up old logs of the command.
+    fn close_log(&self, log: &LogFile, name: &str, code: Option<i32>) -> Result<(), HoiError> {
+        log.write_line(format!("Exited with code: {:?}", code).as_bytes())?;
+
+        // Old logs are only cleaned up on a best-effort basis; a failure here shouldn't fail the run
+        let _ = apply_retention(&self.log_dir(), name, &self.hoi.logs);
+        Ok(())
+    }
+
+    /// Returns the directory logs are written to.
+    fn log_dir(&self) -> PathBuf {
+        self.log_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(".hoi").join("logs"))
+    }
+
+    /// Returns the vars available to scripts: the configured ones and the values of the
+    /// matrix combination being run.
+    fn vars(&self) -> Cow<'_, IndexMap<String, String>> {
+        if self.matrix.is_empty() {
+            return Cow::Borrowed(&self.hoi.vars);
+        }
+
+        let mut vars = self.hoi.vars.clone();
+        vars.extend(self.matrix.clone());
+        Cow::Owned(vars)
+    }
+
+    /// Runs the steps of a prepared command one after another, stopping at the first one that
+    /// fails unless it ignores errors.
+    fn run_steps(
+        &self,
+        invocation: &Invocation,
+        command: &UserCommand,
+        masker: &Masker,
+        log: Option<&LogFile>,
+        running: &mut Vec<String>,
+    ) -> Result<ExitStatus, HoiError> {
+        let total = invocation.steps.len();
+        let mut status = ExitStatus::default();
+
+        for (index, step) in invocation.steps.iter().enumerate() {
+            let skipped = self.skip_reason(invocation, command, Some(step))?;
+            if let Some(title) = &step.title {
+                let header = match &skipped {
+                    Some(reason) => {
+                        format!(
+                            "==> [{}/{}] {} (skipped, {})",
+                            index + 1,
+                            total,
+                            title,
+                            reason
+                        )
+                    }
+                    None => format!("==> [{}/{}] {}", index + 1, total, title),
+                };
+                self.report(&header, false, masker, log)?;
+            }
+            if skipped.is_some() {
+                continue;
+            }
+
+            status = match &step.action {
+                StepAction::Process(command_line) => {
+                    self.spawn(&invocation.name, command, step, command_line, masker, log)?
+                }
+                StepAction::Command { name, args } => {
+                    match self.run_nested(name, args, running, log)? {
+                        Outcome::Completed(status) => status,
+                        Outcome::Skipped(reason) => {
+                            let message = format!("Skipped command '{}' ({})", name, reason);
+                            self.report(&message, false, masker, log)?;
+                            ExitStatus::default()
+                        }
+                    }
+                }
+            };
+
+            let Some(title) = step.title.as_deref().filter(|_| !status.success()) else {
+                continue;
+            };
+
+            if step.ignore_errors {
+                let message = format!(
+                    "Step {}/{} ({}) failed with exit code: {:?}, continuing",
+                    index + 1,
+                    total,
+                    title,
+                    status.code()
+                );
+                self.report(&message, true, masker, log)?;
+                status = ExitStatus::default();
+                continue;
+            }
+
+            let message = format!(
+                "Step {}/{} ({}) of command '{}' failed with exit code: {:?}",
+                index + 1,
+                total,
+                title,
+                invocation.name,
+                status.code()
+            );
+            self.report(&message, true, masker, log)?;
+            break;
+        }
+
+        Ok(status)
+    }
+
+    /// Starts the process of a step and waits for it to finish.
+    fn spawn(
+        &self,
+        name: &str,
+        command: &UserCommand,
+        step: &PreparedStep,
+        command_line: &[String],
+        masker: &Masker,
+        log: Option<&LogFile>,
+    ) -> Result<ExitStatus, HoiError> {
+        let mut process = self.process(name, command, Some(step), command_line);
+        process
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+
+        // Combinations of a matrix that run at the same time can't share the terminal's input
+        if self.output_prefix.is_some() {
+            process.stdin(Stdio::null());
+        }
+
+        if log.is_none() && masker.is_empty() && self.output_prefix.is_none() {
+            return Ok(process.status()?);
+        }
+
+        run_piped(process, masker, log, self.output_prefix.as_deref())
+    }
+
+    /// Builds the process for a command line of a command or one of its steps, with the
+    /// environment and directory it runs in.
+    fn process(
+        &self,
+        name: &str,
+        command: &UserCommand,
+        step: Option<&PreparedStep>,
+        command_line: &[String],
+    ) -> Command {
+        let mut process = Command::new(&command_line[0]);
+        process
+            .args(&command_line[1..])
+            .envs(&self.hoi.env)
+            .envs(&command.env)
+            .envs(&self.matrix);
+
+        // Commands from a config file in the current directory or one of its parents run in
+        // the directory of that file, so they work the same from any subdirectory
+        if let Some(dir) = command.dir().filter(|dir| runs_in_config_dir(dir)) {
+            process.current_dir(dir);
+        }
+
+        if let Some(step) = step {
+            process.envs(&step.env);
+            if let Some(dir) = &step.dir {
+                process.current_dir(dir);
+            }
+        }
+
+        // Expose the directory of the config file that defined the command
+        if let Some(dir) = command.dir() {
+            process.env("HOI_CONFIG_DIR", dir);
+        }
+
+        // Let nested hoi calls know they run from another command, which internal commands require
+        process.env(PARENT_COMMAND_VAR, name);
+
+        // Pass the active profile on, so nested hoi calls use it too
+        if let Some(profile) = &self.hoi.active_profile {
+            process.env("HOI_PROFILE", profile);
+        }
+
+        process
+    }
+
+    /// Returns why a command, or one of its steps, is skipped: its `if` condition doesn't
+    /// hold or its `unless` condition does. Returns None if it runs.
+    ///
+    /// Shell tests run like the command or step would, but without its arguments and with
+    /// their output discarded. File paths are relative to the configuration file that defines
+    /// the command.
+    fn skip_reason(
+        &self,
+        invocation: &Invocation,
+        command: &UserCommand,
+        step: Option<&PreparedStep>,
+    ) -> Result<Option<String>, HoiError> {
+        let (r#if, unless) = match step {
+            Some(step) => (&step.r#if, &step.unless),
+            None => (&command.r#if, &command.unless),
+        };
+
+        let holds = |condition: &Condition| {
+            let lookup = |name: &str| {
+                step.and_then(|step| step.env.get(name))
+                    .or_else(|| self.matrix.get(name))
+                    .or_else(|| command.env.get(name))
+                    .or_else(|| self.hoi.env.get(name))
+                    .cloned()
+                    .or_else(|| env::var(name).ok())
+            };
+            let shell = |test: &str| {
+                let command_line =
+                    self.hoi
+                        .command_line(&invocation.name, command, test, &self.vars(), &[]);
+                let mut process = self.process(&invocation.name, command, step, &command_line);
+                process.stdin(Stdio::null()).stdout(Stdio::null());
+                Ok(process.status()?.success())
+            };
+            condition.holds(command.dir().map(Path::new), lookup, shell)
+        };
+
+        if let Some(condition) = r#if {
+            if !holds(condition)? {
+                return Ok(Some(format!("if: {}", condition.describe())));
+            }
+        }
+        if let Some(condition) = unless {
+            if holds(condition)? {
+                return Ok(Some(format!("unless: {}", condition.describe())));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Prints a step header, failure report or result grid, masking secrets, and writes it to
+    /// the log if there is one. Failures are printed to stderr.
+    fn report(
+        &self,
+        message: &str,
+        error: bool,
+        masker: &Masker,
+        log: Option<&LogFile>,
+    ) -> io::Result<()> {
+        let message = match &self.output_prefix {
+            Some(prefix) => message
+                .lines()
+                .map(|line| format!("{}{}", prefix, line))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => message.to_string(),
+        };
+        let masked = masker.mask(&mut message.into_bytes(), true);
+        let masked = String::from_utf8_lossy(&masked);
+
+        if error {
+            eprintln!("{}", masked);
+        } else {
+            println!("{}", masked);
+        }
+
+        match log {
+            Some(log) => log.write_line(masked.as_bytes()),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the values of the secret variables of a command with `***` in a piece of
+    /// text, such as its arguments before they are written to the history.
+    ///
+    /// # Arguments
+    /// * `command_name` - The alias or name of the command whose secrets to mask
+    /// * `text` - The text to mask
+    pub fn mask(&self, command_name: &str, text: &str) -> String {
+        let Some((_, command)) = self.hoi.find_command(command_name) else {
+            return text.to_string();
+        };
+
+        let masker = Masker::new(self.secret_values(command));
+        let masked = masker.mask(&mut text.as_bytes().to_vec(), true);
+        String::from_utf8_lossy(&masked).into_owned()
+    }
+
+    /// Returns the values of the secret variables for a command.
+    ///
+    /// Each secret name is looked up in the command's `env`, the top-level `env`, the
+    /// `vars`, and finally the process environment.
+    fn secret_values(&self, command: &UserCommand) -> Vec<String> {
+        self.hoi
+            .secret
+            .iter()
+            .chain(&self.secrets)
+            .filter_map(|name| {
+                command
+                    .env
+                    .get(name)
+                    .or_else(|| self.hoi.env.get(name))
+                    .or_else(|| self.hoi.vars.get(name))
+                    .cloned()
+                    .or_else(|| env::var(name).ok())
+            })
+            .collect()
+    }
+}
+
+/// A command ready to run, as prepared by [`Runner::prepare`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// The name of the command, with aliases resolved.
+    pub name: String,
+
+    /// The arguments the command runs with, after adding its `prepend_args` and
+    /// `default_args`.
+    pub args: Vec<String>,
+
+    /// The steps of the command. Commands with a single script have one step without a title.
+    pub steps: Vec<PreparedStep>,
+}
+
+impl Invocation {
+    /// Returns the hoi command line the invocation corresponds to, as in `$ hoi test --workspace`.
+    pub fn describe(&self) -> String {
+        let mut words = vec!["hoi".to_string(), self.name.clone()];
+        words.extend(self.args.iter().cloned());
+        format!("$ {}", template::quote_words(&words))
+    }
+}
+
+/// How running a command ended, as returned by [`Runner::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The command ran and exited with this status.
+    Completed(ExitStatus),
+    /// The command didn't run because of its `if` or `unless` condition, described here.
+    Skipped(String),
+}
+
+impl Outcome {
+    /// Returns the exit status of the command, or None if it was skipped.
+    pub fn status(&self) -> Option<ExitStatus> {
+        match self {
+            Outcome::Completed(status) => Some(*status),
+            Outcome::Skipped(_) => None,
+        }
+    }
+
+    /// Returns true if the command ran and failed. Skipped commands haven't failed.
+    pub fn failed(&self) -> bool {
+        self.status().is_some_and(|status| !status.success())
+    }
+}
+
+/// A step of a command, ready to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreparedStep {
+    /// The title shown in the header of the step, or None for the script of a command
+    /// without steps.
+    pub title: Option<String>,
+
+    /// What the step runs.
+    pub action: StepAction,
+
+    /// The directory the step runs in, if it isn't the current directory.
+    pub dir: Option<PathBuf>,
+
+    /// Environment variables set for this step only.
+    pub env: IndexMap<String, String>,
+
+    /// The condition that must hold for the step to run.
+    pub r#if: Option<Condition>,
+
+    /// The condition that skips the step when it holds.
+    pub unless: Option<Condition>,
+
+    /// Whether the next steps run when this one fails.
+    pub ignore_errors: bool,
+}
+
+/// What a step runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepAction {
+    /// A process, given as the program followed by its arguments.
+    Process(Vec<String>),
+    /// Another command, with the arguments to run it with.
+    Command { name: String, args: Vec<String> },
+}
+
+/// Returns true if a command from a config file in this directory runs there: when the
+/// directory is the current directory or one of its parents. Commands from other config
+/// files, such as the global one, run in the current directory.
+fn runs_in_config_dir(dir: &Path) -> bool {
+    env::current_dir()
+        .map(|cwd| cwd.canonicalize().unwrap_or(cwd))
+        .is_ok_and(|cwd| cwd.starts_with(dir))
+}
+
+/// Describes a combination of matrix values, as in `toolchain=stable, features=""`.
+fn matrix_label(combination: &IndexMap<String, String>) -> String {
+    combination
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, matrix_value(value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Shows a matrix value, with empty values as `""` so they stand out.
+pub(crate) fn matrix_value(value: &str) -> String {
+    if value.is_empty() {
+        "\"\"".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Describes the `if` and `unless` conditions of a command or step for a dry run, as comment
+/// lines such as `# if: env CI`.
+fn condition_notes(r#if: &Option<Condition>, unless: &Option<Condition>) -> String {
+    let mut notes = String::new();
+    if let Some(condition) = r#if {
+        notes.push_str(&format!("# if: {}\n", condition.describe()));
+    }
+    if let Some(condition) = unless {
+        notes.push_str(&format!("# unless: {}\n", condition.describe()));
+    }
+    notes
+}
+
+/// Runs a process with its output piped through a masker before it reaches the terminal,
+/// and the log file if one is given. With a prefix, every line of output starts with it.
+///
+/// Output is streamed as it arrives and passed through unchanged apart from the masked
+/// values, so colors are kept. Because the process no longer writes to a terminal, it is
+/// asked to keep its colors with `FORCE_COLOR` and `CLICOLOR_FORCE` when hoi's own output
+/// is a terminal.
+fn run_piped(
+    mut process: Command,
+    masker: &Masker,
+    log: Option<&LogFile>,
+    prefix: Option<&str>,
+) -> Result<ExitStatus, HoiError> {
+    if colors_enabled() {
+        process.env("FORCE_COLOR", "1").env("CLICOLOR_FORCE", "1");
+    }
+
+    let mut child = process
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let output = |terminal: Box<dyn Write + Send>| -> Box<dyn Write + Send> {
+        let writer: Box<dyn Write + Send> = match log {
+            Some(log) => Box::new(Tee(terminal, log.writer())),
+            None => terminal,
+        };
+        match prefix {
+            Some(prefix) => Box::new(LinePrefix::new(writer, prefix)),
+            None => writer,
+        }
+    };
+    let stdout_writer = output(Box::new(io::stdout()));
+    let stderr_writer = output(Box::new(io::stderr()));
+
+    thread::scope(|scope| {
+        let out = scope.spawn(|| pump(stdout, stdout_writer, masker));
+        let err = scope.spawn(|| pump(stderr, stderr_writer, masker));
+        let status = child.wait()?;
+
+        out.join().expect("stdout thread panicked")?;
+        err.join().expect("stderr thread panicked")?;
+
+        Ok(status)
+    })
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::fs;
+    use temp_env::{with_var, with_vars};
+    use testdir::testdir;
+    use utilities::copy_fixture;
+
+    #[test]
+    fn test_run_returns_exit_status() {
+        let temp_dir = testdir!();
+        copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+        let mut hoi = crate::config::load_config(&temp_dir.join(".hoi.yml")).unwrap();
+        let runner = Runner::new(&hoi);
+
+        let status = runner.run("echo-test", &[]).unwrap().status().unwrap();
+        assert!(status.success());
+
+        assert!(matches!(
+            runner.run("missing", &[]),
+            Err(HoiError::CommandNotFound(_))
+        ));
+
+        hoi.commands.get_mut("echo-test").unwrap().cmd = "exit 3".into();
+        let status = Runner::new(&hoi)
+            .run("echo-test", &[])
+            .unwrap()
+            .status()
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let command = hoi.commands.get_mut("echo-test").unwrap();
+        command.cmd = "test \"$HOI_TEST_VAR\" = command".into();
+        command.env.insert("HOI_TEST_VAR".into(), "command".into());
+        command.entrypoint = Some(vec!["sh".into(), "-c".into(), "$@".into()]);
+        let status = Runner::new(&hoi)
+            .run("echo-test", &[])
+            .unwrap()
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_run_passes_arguments() {
+        let temp_dir = testdir!();
+        let hoi: Hoi = serde_yaml::from_str(&format!(
+            r#"
+commands:
+  positional:
+    cmd: test "$0 $1 $#" = "positional one 2"
+  placeholders:
+    cmd: test {{{{target}}}} = "two words" && test {{{{args[1:]}}}} = latest
+    args:
+      - name: target
+        required: true
+  append:
+    cmd: test "$0 $#" = "append 0" && test
+    pass_args: append
+  none:
+    cmd: test $# -eq 0
+    pass_args: none
+  log:
+    cmd: echo {{{{args}}}} > {}
+"#,
+            temp_dir.join("log.txt").display()
+        ))
+        .unwrap();
+        let runner = Runner::new(&hoi);
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(runner
+            .run("positional", &args(&["one", "two"]))
+            .unwrap()
+            .status()
+            .unwrap()
+            .success());
+        assert!(runner
+            .run("placeholders", &args(&["two words", "latest"]))
+            .unwrap()
+            .status()
+            .unwrap()
+            .success());
+        assert!(matches!(
+            runner.run("placeholders", &[]),
+            Err(HoiError::MissingArgument { .. })
+        ));
+        assert!(runner
+            .run("append", &args(&["-n", "x"]))
+            .unwrap()
+            .status()
+            .unwrap()
+            .success());
+        assert!(runner
+            .run("none", &args(&["ignored"]))
+            .unwrap()
+            .status()
+            .unwrap()
+            .success());
+
+        // Quoting keeps arguments from being run as commands
+        runner.run("log", &args(&["$(echo no)", "it's"])).unwrap();
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("log.txt")).unwrap(),
+            "$(echo no) it's\n"
+        );
+    }
+
+    #[test]
+    fn test_prepare_adds_default_and_prepended_args() {
+        let hoi: Hoi = serde_yaml::from_str(
+            r#"
+entrypoint: ["sh", "-c", "$@"]
+secret: [TOKEN]
+env:
+  TOKEN: hunter2
+vars:
+  token: hunter2
+commands:
+  test:
+    cmd: cargo test --token {{token}}
+    alias: t
+    pass_args: append
+    prepend_args: ["--color", "always"]
+    default_args: ["--workspace"]
+"#,
+        )
+        .unwrap();
+        let runner = Runner::new(&hoi);
+
+        let invocation = runner.prepare("t", &[]).unwrap();
+        assert_eq!(invocation.name, "test");
+        assert_eq!(invocation.args, vec!["--color", "always", "--workspace"]);
+        assert_eq!(
+            invocation.steps[0].action,
+            StepAction::Process(
+                [
+                    "sh",
+                    "-c",
+                    "cargo test --token hunter2 --color always --workspace",
+                    "test"
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+
+        let args = vec!["my test".to_string()];
+        assert_eq!(
+            runner.prepare("test", &args).unwrap().args,
+            vec!["--color", "always", "my test"]
+        );
+        assert_eq!(
+            runner.dry_run("test", &args).unwrap(),
+            "$ hoi test --color always 'my test'\n\
+             sh -c 'cargo test --token *** --color always '\\''my test'\\''' test\n"
+        );
+
+        // Without placeholders or pass_args, the arguments are added to the end of the command
+        let hoi: Hoi = serde_yaml::from_str(
+            r#"
+entrypoint: ["sh", "-c", "$@"]
+commands:
+  test:
+    cmd: cargo test
+    default_args: ["--workspace"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Runner::new(&hoi).prepare("test", &[]).unwrap().steps[0].action,
+            StepAction::Process(
+                ["sh", "-c", "cargo test --workspace", "test"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_steps() {
+        let temp_dir = testdir!();
+        fs::create_dir_all(temp_dir.join("web")).unwrap();
+        let config_path = temp_dir.join(".hoi.yml");
+        let contents = format!(
+            r#"
+root: true
+env:
+  OUT: {out}
+commands:
+  build:
+    cmd:
+      - echo "build $1" >> "$OUT/steps.txt"
+      - name: Bundle
+        cmd: echo "bundle $STAGE $(basename "$PWD")" >> "$OUT/steps.txt"
+        dir: web
+        env:
+          STAGE: prod
+      - run: lint
+        args: ["--fix"]
+      - cmd: exit 3
+        ignore_errors: true
+      - echo done >> "$OUT/steps.txt"
+  lint:
+    cmd: echo "lint $1" >> "$OUT/steps.txt"
+  broken:
+    cmd:
+      - echo first >> "$OUT/broken.txt"
+      - exit 4
+      - echo never >> "$OUT/broken.txt"
+  loop:
+    cmd:
+      - run: again
+  again:
+    cmd:
+      - run: loop
+  invalid:
+    cmd:
+      - run: lint
+        cmd: echo both
+"#,
+            out = temp_dir.display()
+        );
+        fs::write(&config_path, contents).unwrap();
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        let config = with_var(env_var, Some(&temp_dir), || {
+            Config::discover(&temp_dir).unwrap()
+        });
+        let runner = Runner::new(&config.hoi);
+
+        let status = runner
+            .run("build", &["release".to_string()])
+            .unwrap()
+            .status()
+            .unwrap();
+        let broken = runner.run("broken", &[]).unwrap().status().unwrap();
+        let looped = runner.run("loop", &[]);
+        let invalid = runner.run("invalid", &[]);
+
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("steps.txt")).unwrap(),
+            "build release\nbundle prod web\nlint --fix\ndone\n"
+        );
+
+        assert_eq!(broken.code(), Some(4));
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("broken.txt")).unwrap(),
+            "first\n"
+        );
+
+        assert!(
+            matches!(looped, Err(HoiError::CommandLoop(ref chain)) if chain == "loop -> again -> loop")
+        );
+        assert!(matches!(
+            invalid,
+            Err(HoiError::InvalidStep { step: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_skips_commands_and_steps() {
+        let temp_dir = testdir!();
+        fs::write(temp_dir.join("Cargo.lock"), "").unwrap();
+        let contents = format!(
+            r#"
+root: true
+env:
+  OUT: {out}
+  ENABLED: "yes"
+commands:
+  check:
+    cmd:
+      - cmd: echo "on CI" >> "$OUT/check.txt"
+        if:
+          env: HOI_TEST_UNSET_CI
+      - cmd: echo "lock file" >> "$OUT/check.txt"
+        if:
+          file: Cargo.lock
+          env: ENABLED
+      - cmd: echo "not on {os}" >> "$OUT/check.txt"
+        unless:
+          platform: {os}
+      - run: gated
+      - echo done >> "$OUT/check.txt"
+  gated:
+    cmd: echo gated >> "$OUT/check.txt"
+    if: test -f "$OUT/missing.txt"
+  unless:
+    cmd: echo never >> "$OUT/check.txt"
+    unless: test -f "$HOI_CONFIG_DIR/Cargo.lock"
+"#,
+            out = temp_dir.display(),
+            os = env::consts::OS
+        );
+        fs::write(temp_dir.join(".hoi.yml"), contents).unwrap();
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        let config = with_var(env_var, Some(&temp_dir), || {
+            Config::discover(&temp_dir).unwrap()
+        });
+        let runner = Runner::new(&config.hoi);
+
+        let outcome = runner.run("check", &[]).unwrap();
+        assert!(!outcome.failed());
+        assert!(outcome.status().unwrap().success());
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("check.txt")).unwrap(),
+            "lock file\ndone\n"
+        );
+
+        let outcome = runner.run("gated", &[]).unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Skipped("if: `test -f \"$OUT/missing.txt\"`".to_string())
+        );
+        assert!(!outcome.failed());
+
+        let preview = runner.dry_run("check", &[]).unwrap();
+        assert!(
+            preview.contains("\n# if: env HOI_TEST_UNSET_CI\n"),
+            "{}",
+            preview
+        );
+        assert!(
+            preview.contains(&format!("\n# unless: platform {}\n", env::consts::OS)),
+            "{}",
+            preview
+        );
+
+        let outcome = runner.run("unless", &[]).unwrap();
+        assert!(matches!(outcome, Outcome::Skipped(ref reason) if reason.starts_with("unless: ")));
+        assert!(!fs::read_to_string(temp_dir.join("check.txt"))
+            .unwrap()
+            .contains("never"));
+    }
+
+    #[test]
+    fn test_run_checks_environment() {
+        let temp_dir = testdir!();
+        fs::write(
+            temp_dir.join(".hoi.yml"),
+            r#"
+root: true
+env_required: [HOI_TEST_REGION]
+env:
+  HOI_TEST_REGION: eu-west-1
+commands:
+  deploy:
+    cmd:
+      - run: migrate
+      - echo deploying
+    env_required:
+      - HOI_TEST_TOKEN
+  migrate:
+    cmd: echo migrating
+    env_required:
+      - HOI_TEST_DATABASE_URL: "^postgres://"
+  publish:
+    cmd:
+      - run: migrate
+        if:
+          env: HOI_TEST_UNSET_CI
+      - echo publishing
+  release:
+    cmd: echo releasing
+    if:
+      env: HOI_TEST_UNSET_CI
+    env_required:
+      - HOI_TEST_TOKEN
+"#,
+        )
+        .unwrap();
+
+        let config = with_var("HOME", Some(&temp_dir), || {
+            Config::discover(&temp_dir).unwrap()
+        });
+        let runner = Runner::new(&config.hoi)
+            .with_environment_files(config.environment_files(&config.hoi.commands["deploy"]));
+
+        with_vars(
+            [
+                ("HOI_TEST_TOKEN", None),
+                ("HOI_TEST_UNSET_CI", None),
+                ("HOI_TEST_DATABASE_URL", Some("mysql://localhost/app")),
+            ],
+            || {
+                match runner.run("deploy", &[]) {
+                    Err(HoiError::EnvironmentNotSet {
+                        command,
+                        problems,
+                        hint,
+                    }) => {
+                        assert_eq!(command, "deploy");
+                        assert_eq!(problems, vec!["HOI_TEST_TOKEN: not set"]);
+                        assert!(hint.contains(&temp_dir.join(".env.local").display().to_string()));
+                    }
+                    other => panic!("expected missing variables, got {:?}", other),
+                }
+
+                // Skipped commands and steps don't need their variables
+                assert!(matches!(
+                    runner.run("release", &[]),
+                    Ok(Outcome::Skipped(_))
+                ));
+                assert!(runner
+                    .run("publish", &[])
+                    .unwrap()
+                    .status()
+                    .unwrap()
+                    .success());
+            },
+        );
+
+        with_vars(
+            [
+                ("HOI_TEST_TOKEN", Some("secret")),
+                ("HOI_TEST_DATABASE_URL", Some("mysql://localhost/app")),
+            ],
+            || match runner.run("deploy", &[]) {
+                Err(HoiError::EnvironmentNotSet {
+                    command, problems, ..
+                }) => {
+                    assert_eq!(command, "migrate");
+                    assert_eq!(
+                        problems,
+                        vec!["HOI_TEST_DATABASE_URL: does not match ^postgres://"]
+                    );
+                }
+                other => panic!("expected an invalid variable, got {:?}", other),
+            },
+        );
+
+        with_vars(
+            [
+                ("HOI_TEST_TOKEN", Some("secret")),
+                ("HOI_TEST_DATABASE_URL", Some("postgres://localhost/app")),
+            ],
+            || {
+                assert!(runner
+                    .run("deploy", &[])
+                    .unwrap()
+                    .status()
+                    .unwrap()
+                    .success())
+            },
+        );
+    }
+
+    #[test]
+    fn test_run_matrix() {
+        let temp_dir = testdir!();
+        let contents = format!(
+            r#"
+root: true
+env:
+  OUT: {out}
+commands:
+  check:
+    matrix:
+      toolchain: [stable, beta]
+      features: ["", serde]
+    cmd:
+      - echo "{{{{toolchain}}}} $features" >> "$OUT/check.txt"
+      - cmd: echo "beta $features" >> "$OUT/beta.txt"
+        if:
+          env: HOI_TEST_UNSET_MATRIX
+    if: test "$toolchain" != nightly
+  parallel:
+    matrix:
+      version: ["3.10", 3.12]
+    parallel: true
+    cmd: echo "$version" > "$OUT/parallel-$version.txt" && test "$version" = 3.10
+  all:
+    cmd:
+      - run: check
+      - run: parallel
+  empty:
+    matrix:
+      version: []
+    cmd: echo never
+"#,
+            out = temp_dir.display(),
+        );
+        fs::write(temp_dir.join(".hoi.yml"), contents).unwrap();
+        #[cfg(not(windows))]
+        let env_var = "HOME";
+        #[cfg(windows)]
+        let env_var = "USERPROFILE";
+
+        let config = with_var(env_var, Some(&temp_dir), || {
+            Config::discover(&temp_dir).unwrap()
+        });
+        let runner = Runner::new(&config.hoi);
+
+        let outcome = runner.run("check", &[]).unwrap();
+        assert!(outcome.status().unwrap().success());
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("check.txt")).unwrap(),
+            "stable \nstable serde\nbeta \nbeta serde\n"
+        );
+        assert!(!temp_dir.join("beta.txt").exists());
+
+        // Every combination runs, and the first failure is the outcome
+        let outcome = runner.run("parallel", &[]).unwrap();
+        assert!(outcome.failed());
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("parallel-3.10.txt")).unwrap(),
+            "3.10\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("parallel-3.12.txt")).unwrap(),
+            "3.12\n"
+        );
+
+        assert!(runner.run("all", &[]).unwrap().failed());
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("check.txt"))
+                .unwrap()
+                .lines()
+                .count(),
+            8
+        );
+
+        let preview = runner.dry_run("parallel", &[]).unwrap();
+        assert!(
+            preview.contains("# matrix, 2 combinations in parallel:\n#   version=3.10\n"),
+            "{}",
+            preview
+        );
+
+        assert!(matches!(
+            runner.run("empty", &[]),
+            Err(HoiError::InvalidMatrix { .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_respects_profiles() {
+        let temp_dir = testdir!();
+        copy_fixture(".hoi.profiles.yml", &temp_dir, ".hoi.yml");
+        let mut hoi = crate::config::load_config(&temp_dir.join(".hoi.yml")).unwrap();
+
+        assert!(matches!(
+            Runner::new(&hoi).run("deploy", &[]),
+            Err(HoiError::ProfileNotAllowed { .. })
+        ));
+
+        hoi.apply_profile("prod", &temp_dir.join(".hoi.yml"))
+            .unwrap();
+        let status = Runner::new(&hoi)
+            .run("deploy", &[])
+            .unwrap()
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
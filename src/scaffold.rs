@@ -0,0 +1,791 @@
+use crate::format::{find_config_in_dir, ConfigFormat};
+use crate::hoi::HoiError;
+use std::fs;
+use std::path::Path;
+
+/// The header that points editors at the JSON Schema for YAML configuration files.
+pub const SCHEMA_HEADER: &str = "# yaml-language-server: $schema=https://raw.githubusercontent.com/kevinquillen/hoi/main/schema/hoi.schema.json";
+
+/// The names of the templates that ship with Hoi.
+pub const BUILTIN_TEMPLATES: [&str; 3] = ["default", "rust", "docker"];
+
+/// The example configuration used when nothing more specific applies.
+const DEFAULT_TEMPLATE: &str = r#"version: 1
+description: "Custom commands"
+commands:
+  hello:
+    cmd: echo "Hello from Hoi!"
+    description: "A simple example command."
+  multiline:
+    cmd: |
+      echo "This is a multi-line command"
+      echo "Each line will be executed in sequence"
+    alias: ml
+    description: "An example of a multi-line command with an alias."
+  help:
+    cmd: |
+      echo "To add new commands, edit the .hoi.yml file in this directory."
+      echo "Run 'hoi' to see a list of all available commands."
+    description: "Shows help information about using hoi."
+"#;
+
+/// A command to add to a new configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScaffoldCommand {
+    /// The name of the command.
+    pub name: String,
+
+    /// The command to run.
+    pub cmd: String,
+
+    /// A description of the command, shown in the command listing.
+    pub description: String,
+}
+
+impl ScaffoldCommand {
+    fn new(name: &str, cmd: &str, description: &str) -> ScaffoldCommand {
+        ScaffoldCommand {
+            name: name.to_string(),
+            cmd: cmd.to_string(),
+            description: description.to_string(),
+        }
+    }
+}
+
+/// The commands detected from the project files in a directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Detection {
+    /// The project files commands were detected from, such as Cargo.toml.
+    pub files: Vec<String>,
+
+    /// The detected commands. When several project files define a command with the same
+    /// name, the first one wins.
+    pub commands: Vec<ScaffoldCommand>,
+}
+
+/// The contents of a template and the format it is written in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// The contents of the configuration file to create.
+    pub contents: String,
+
+    /// The format the template is written in, which decides the file extension.
+    pub format: ConfigFormat,
+}
+
+/// Detects the type of project in a directory and the commands that go with it:
+///
+/// * Cargo.toml: build, test, clippy and fmt
+/// * package.json: its scripts, run with npm, yarn or pnpm depending on the lock file
+/// * Makefile: its targets
+/// * docker-compose.yml or compose.yml: up and down
+///
+/// # Arguments
+/// * `dir` - The directory to look in
+pub fn detect_project(dir: &Path) -> Detection {
+    let mut detection = Detection::default();
+
+    if dir.join("Cargo.toml").exists() {
+        detection.add("Cargo.toml", cargo_commands());
+    }
+
+    if let Ok(contents) = fs::read_to_string(dir.join("package.json")) {
+        detection.add("package.json", package_json_commands(dir, &contents, None));
+    }
+
+    for makefile in ["Makefile", "makefile", "GNUmakefile"] {
+        if let Ok(contents) = fs::read_to_string(dir.join(makefile)) {
+            detection.add(makefile, makefile_commands(&contents, "make"));
+            break;
+        }
+    }
+
+    for compose in [
+        "docker-compose.yml",
+        "docker-compose.yaml",
+        "compose.yml",
+        "compose.yaml",
+    ] {
+        if dir.join(compose).exists() {
+            detection.add(compose, compose_commands());
+            break;
+        }
+    }
+
+    detection
+}
+
+impl Detection {
+    /// Adds the commands detected from a project file, skipping names that are already taken.
+    fn add(&mut self, file: &str, commands: Vec<ScaffoldCommand>) {
+        if commands.is_empty() {
+            return;
+        }
+
+        self.files.push(file.to_string());
+        for command in commands {
+            if !self.commands.iter().any(|c| c.name == command.name) {
+                self.commands.push(command);
+            }
+        }
+    }
+}
+
+/// Returns the commands for a Rust project.
+fn cargo_commands() -> Vec<ScaffoldCommand> {
+    vec![
+        ScaffoldCommand::new("build", "cargo build", "Builds the project."),
+        ScaffoldCommand::new("test", "cargo test", "Runs the tests."),
+        ScaffoldCommand::new(
+            "clippy",
+            "cargo clippy --all-targets -- -D warnings",
+            "Lints the code with Clippy.",
+        ),
+        ScaffoldCommand::new("fmt", "cargo fmt", "Formats the code."),
+    ]
+}
+
+/// Returns the commands for a Docker Compose project.
+fn compose_commands() -> Vec<ScaffoldCommand> {
+    vec![
+        ScaffoldCommand::new("up", "docker compose up -d", "Starts the containers."),
+        ScaffoldCommand::new("down", "docker compose down", "Stops the containers."),
+    ]
+}
+
+/// Returns a command for each script in a package.json file, described by the script itself.
+///
+/// # Arguments
+/// * `dir` - The directory of the package.json file, used to pick npm, yarn or pnpm
+/// * `contents` - The contents of the package.json file
+/// * `prefix` - How to point the package manager at `dir`, such as `--prefix web`, if it
+///   isn't the current directory
+fn package_json_commands(dir: &Path, contents: &str, prefix: Option<&str>) -> Vec<ScaffoldCommand> {
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(contents) else {
+        return Vec::new();
+    };
+    let Some(scripts) = package.get("scripts").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+
+    let (runner, dir_flag) = if dir.join("pnpm-lock.yaml").exists() {
+        ("pnpm", "--dir")
+    } else if dir.join("yarn.lock").exists() {
+        ("yarn", "--cwd")
+    } else {
+        ("npm", "--prefix")
+    };
+    let runner = match prefix {
+        Some(prefix) => format!("{} {} {} run", runner, dir_flag, prefix),
+        None => format!("{} run", runner),
+    };
+
+    scripts
+        .iter()
+        .map(|(script, body)| {
+            let description = match body.as_str() {
+                Some(body) => format!("Runs `{}` from package.json.", body),
+                None => format!("Runs the {} script from package.json.", script),
+            };
+            ScaffoldCommand::new(script, &format!("{} {}", runner, script), &description)
+        })
+        .collect()
+}
+
+/// Returns a command for each target in a Makefile. Special targets like `.PHONY`,
+/// pattern rules and variable assignments are skipped.
+///
+/// Targets are described by a `##` help comment after the target (`build: ## Builds it`)
+/// or on the line before it, the same comments `make help` targets commonly use.
+///
+/// # Arguments
+/// * `contents` - The contents of the Makefile
+/// * `make` - The command that runs a target, such as `make` or `make -f other.mk`
+fn makefile_commands(contents: &str, make: &str) -> Vec<ScaffoldCommand> {
+    let mut commands = Vec::new();
+    let mut help: Option<String> = None;
+
+    for line in contents.lines() {
+        if let Some(comment) = line.strip_prefix("##") {
+            help = Some(comment.trim().to_string());
+            continue;
+        }
+
+        let comment = help.take();
+        if line.starts_with(['\t', ' ', '#', '.']) {
+            continue;
+        }
+        let Some((targets, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let rest = rest.strip_prefix(':').unwrap_or(rest);
+        if rest.starts_with('=') || targets.contains('=') {
+            continue;
+        }
+
+        let description = rest
+            .split_once("##")
+            .map(|(_, help)| help.trim().to_string())
+            .filter(|help| !help.is_empty())
+            .or(comment);
+
+        for target in targets.split_whitespace() {
+            let valid = target
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+            if valid && !commands.iter().any(|c: &ScaffoldCommand| c.name == target) {
+                let description = description
+                    .clone()
+                    .unwrap_or_else(|| format!("Runs the {} target from the Makefile.", target));
+                commands.push(ScaffoldCommand::new(
+                    target,
+                    &format!("{} {}", make, target),
+                    &description,
+                ));
+            }
+        }
+    }
+
+    commands
+}
+
+/// Returns a command for each public recipe in a justfile, described by its doc comment.
+/// Private recipes (named with a leading `_` or marked `[private]`) are skipped.
+///
+/// # Arguments
+/// * `contents` - The contents of the justfile
+/// * `just` - The command that runs a recipe, such as `just` or `just --justfile other.just`
+fn justfile_commands(contents: &str, just: &str) -> Vec<ScaffoldCommand> {
+    let mut commands = Vec::new();
+    let mut doc: Option<String> = None;
+    let mut private = false;
+
+    for line in contents.lines() {
+        if line.trim().is_empty() || line.starts_with([' ', '\t']) {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            if !comment.starts_with('!') {
+                doc = Some(comment.trim().to_string());
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+
+        let (comment, is_private) = (doc.take(), std::mem::take(&mut private));
+        let keyword = line.split_whitespace().next().unwrap_or_default();
+        if line.contains(":=") || matches!(keyword, "set" | "alias" | "export" | "import" | "mod") {
+            continue;
+        }
+        let Some((head, _)) = line.split_once(':') else {
+            continue;
+        };
+
+        let name = head
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('@');
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if valid && !name.starts_with('_') && !is_private {
+            let description =
+                comment.unwrap_or_else(|| format!("Runs the {} recipe from the justfile.", name));
+            commands.push(ScaffoldCommand::new(
+                name,
+                &format!("{} {}", just, name),
+                &description,
+            ));
+        }
+    }
+
+    commands
+}
+
+/// Returns a command for each process type in a Procfile, running its command directly.
+///
+/// # Arguments
+/// * `contents` - The contents of the Procfile
+fn procfile_commands(contents: &str) -> Vec<ScaffoldCommand> {
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, cmd)| (name.trim(), cmd.trim()))
+        .filter(|(name, cmd)| {
+            !cmd.is_empty()
+                && !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        })
+        .map(|(name, cmd)| {
+            ScaffoldCommand::new(
+                name,
+                cmd,
+                &format!("Runs the {} process from the Procfile.", name),
+            )
+        })
+        .collect()
+}
+
+/// Reads the commands defined by a task runner file. The kind of file is chosen by its
+/// name: a Makefile (or `*.mk` file), package.json, a justfile (or `*.just` file), or a
+/// Procfile.
+///
+/// Paths are used as given, so a file outside the current directory is imported with
+/// commands like `make -f <path> <target>` that still work from the current directory.
+///
+/// # Arguments
+/// * `path` - The path to the file to import
+///
+/// # Errors
+/// * `HoiError::UnsupportedImport` - If the kind of file can't be determined
+/// * `HoiError::UnreadableConfig` - If the file can't be read
+pub fn import_commands(path: &Path) -> Result<Vec<ScaffoldCommand>, HoiError> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+
+    let read = || {
+        fs::read_to_string(path).map_err(|source| HoiError::UnreadableConfig {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+
+    let commands = match file_name.as_str() {
+        "Makefile" | "makefile" | "GNUmakefile" if dir.is_none() => {
+            makefile_commands(&read()?, "make")
+        }
+        "Makefile" | "makefile" | "GNUmakefile" => {
+            makefile_commands(&read()?, &format!("make -f {}", path.display()))
+        }
+        _ if extension == Some("mk") => {
+            makefile_commands(&read()?, &format!("make -f {}", path.display()))
+        }
+        "package.json" => {
+            let prefix = dir.map(|dir| dir.display().to_string());
+            package_json_commands(dir.unwrap_or(Path::new(".")), &read()?, prefix.as_deref())
+        }
+        "justfile" | "Justfile" | ".justfile" if dir.is_none() => {
+            justfile_commands(&read()?, "just")
+        }
+        _ if file_name.eq_ignore_ascii_case("justfile")
+            || file_name == ".justfile"
+            || extension == Some("just") =>
+        {
+            justfile_commands(&read()?, &format!("just --justfile {}", path.display()))
+        }
+        _ if file_name == "Procfile" || file_name.starts_with("Procfile.") => {
+            procfile_commands(&read()?)
+        }
+        _ => return Err(HoiError::UnsupportedImport(path.to_path_buf())),
+    };
+
+    Ok(commands)
+}
+
+/// Adds commands to the contents of an existing configuration file, keeping everything that
+/// is already in it, comments included for YAML and TOML.
+///
+/// YAML commands are inserted at the end of the `commands` block, matching its indentation,
+/// and TOML commands are appended as `[commands.<name>]` tables. JSON files are re-serialized
+/// with the new commands added.
+///
+/// # Arguments
+/// * `path` - The path of the configuration file, which decides its format
+/// * `contents` - The contents of the configuration file
+/// * `commands` - The commands to add; these should not already be defined
+///
+/// # Errors
+/// * `HoiError::InvalidConfig` - If a JSON file can't be parsed, or a YAML file writes
+///   `commands` in flow style (`{...}`)
+pub fn add_commands(
+    path: &Path,
+    contents: &str,
+    commands: &[ScaffoldCommand],
+) -> Result<String, HoiError> {
+    match ConfigFormat::from_path(path) {
+        ConfigFormat::Yaml => add_yaml_commands(path, contents, commands),
+        ConfigFormat::Toml => {
+            let mut toml = contents.to_string();
+            for command in commands {
+                if !toml.is_empty() && !toml.ends_with("\n\n") {
+                    toml.push_str(if toml.ends_with('\n') { "\n" } else { "\n\n" });
+                }
+                toml.push_str(&format!(
+                    "[commands.{}]\ncmd = {}\ndescription = {}\n",
+                    key(&command.name),
+                    quote(&command.cmd),
+                    quote(&command.description)
+                ));
+            }
+            Ok(toml)
+        }
+        ConfigFormat::Json => {
+            let mut config: serde_json::Map<String, serde_json::Value> =
+                ConfigFormat::Json.parse_file(path, contents)?;
+            let existing = config
+                .entry("commands")
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            if let serde_json::Value::Object(existing) = existing {
+                for command in commands {
+                    existing.insert(
+                        command.name.clone(),
+                        serde_json::json!({ "cmd": command.cmd, "description": command.description }),
+                    );
+                }
+            }
+            Ok(serde_json::to_string_pretty(&config)? + "\n")
+        }
+    }
+}
+
+/// Inserts commands at the end of the `commands` block of a YAML file, or adds the block.
+fn add_yaml_commands(
+    path: &Path,
+    contents: &str,
+    commands: &[ScaffoldCommand],
+) -> Result<String, HoiError> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let Some(start) = lines.iter().position(|line| line.starts_with("commands:")) else {
+        let mut yaml = contents.to_string();
+        if !yaml.is_empty() && !yaml.ends_with('\n') {
+            yaml.push('\n');
+        }
+        yaml.push_str("commands:\n");
+        yaml.push_str(&render_commands(commands, "  "));
+        return Ok(yaml);
+    };
+
+    let rest = lines[start]["commands:".len()..].trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(HoiError::InvalidConfig {
+            path: path.to_path_buf(),
+            message: "commands can only be imported into a block-style `commands:` mapping"
+                .to_string(),
+        });
+    }
+
+    // The block ends at the next top-level key; trailing blank lines and comments stay after it
+    let is_top_level = |line: &&str| !line.is_empty() && !line.starts_with([' ', '\t', '#']);
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(is_top_level)
+        .map(|i| start + 1 + i)
+        .unwrap_or(lines.len());
+    while end > start + 1 && (lines[end - 1].trim().is_empty() || lines[end - 1].starts_with('#')) {
+        end -= 1;
+    }
+
+    let indent = lines[start + 1..end]
+        .iter()
+        .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .unwrap_or("  ");
+
+    let mut yaml = String::new();
+    for line in &lines[..end] {
+        yaml.push_str(line);
+        yaml.push('\n');
+    }
+    yaml.push_str(&render_commands(commands, indent));
+    for line in &lines[end..] {
+        yaml.push_str(line);
+        yaml.push('\n');
+    }
+
+    Ok(yaml)
+}
+
+/// Renders commands as entries of a YAML `commands` mapping.
+fn render_commands(commands: &[ScaffoldCommand], indent: &str) -> String {
+    commands
+        .iter()
+        .map(|command| {
+            format!(
+                "{indent}{}:\n{indent}{indent}cmd: {}\n{indent}{indent}description: {}\n",
+                key(&command.name),
+                quote(&command.cmd),
+                quote(&command.description)
+            )
+        })
+        .collect()
+}
+
+/// Renders a YAML configuration file with the given commands.
+///
+/// # Arguments
+/// * `description` - The description of the command set
+/// * `commands` - The commands to add
+pub fn render_config(description: &str, commands: &[ScaffoldCommand]) -> String {
+    let mut yaml = format!(
+        "{}\nversion: 1\ndescription: {}\ncommands:\n",
+        SCHEMA_HEADER,
+        quote(description)
+    );
+
+    yaml.push_str(&render_commands(commands, "  "));
+    yaml
+}
+
+/// Returns a built-in template by name.
+///
+/// # Arguments
+/// * `name` - One of [`BUILTIN_TEMPLATES`]
+pub fn builtin_template(name: &str) -> Option<String> {
+    match name {
+        "default" => Some(format!("{}\n{}", SCHEMA_HEADER, DEFAULT_TEMPLATE)),
+        "rust" => Some(render_config("Rust project commands", &cargo_commands())),
+        "docker" => Some(render_config(
+            "Docker Compose commands",
+            &compose_commands(),
+        )),
+        _ => None,
+    }
+}
+
+/// Finds a template by name. Templates in the user's template directory, such as
+/// `~/.hoi/templates/<name>.yml`, take precedence over the built-in ones and may be
+/// written in any supported format.
+///
+/// # Arguments
+/// * `name` - The name of the template
+/// * `templates_dir` - The directory with the user's templates, if any
+///
+/// # Errors
+/// * `HoiError::TemplateNotFound` - If there is no template with this name
+/// * `HoiError::Io` - If the user's template can't be read
+pub fn find_template(name: &str, templates_dir: Option<&Path>) -> Result<Template, HoiError> {
+    if let Some(path) = templates_dir.and_then(|dir| find_config_in_dir(dir, name)) {
+        return Ok(Template {
+            contents: fs::read_to_string(&path)?,
+            format: ConfigFormat::from_path(&path),
+        });
+    }
+
+    if let Some(contents) = builtin_template(name) {
+        return Ok(Template {
+            contents,
+            format: ConfigFormat::Yaml,
+        });
+    }
+
+    Err(HoiError::TemplateNotFound(
+        name.to_string(),
+        available_templates(templates_dir).join(", "),
+    ))
+}
+
+/// Returns the names of the built-in templates and the user's templates, sorted.
+///
+/// # Arguments
+/// * `templates_dir` - The directory with the user's templates, if any
+pub fn available_templates(templates_dir: Option<&Path>) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_TEMPLATES.iter().map(|s| s.to_string()).collect();
+
+    if let Some(Ok(entries)) = templates_dir.map(fs::read_dir) {
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let supported = ConfigFormat::ALL.iter().any(|format| {
+                path.extension()
+                    .is_some_and(|ext| ext == format.extension())
+            });
+            if let (true, Some(stem)) = (supported, path.file_stem()) {
+                names.push(stem.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Quotes a YAML value. JSON strings are valid double-quoted YAML scalars.
+fn quote(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+/// Quotes a YAML key unless it is a plain name.
+fn key(name: &str) -> String {
+    let plain = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if plain {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hoi::Hoi;
+    use testdir::testdir;
+
+    #[test]
+    fn test_detect_project() {
+        let temp_dir = testdir!();
+        fs::write(temp_dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::write(
+            temp_dir.join("package.json"),
+            r#"{"scripts": {"build": "vite build", "dev": "vite", "test:e2e": "playwright test"}}"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.join("yarn.lock"), "").unwrap();
+        fs::write(
+            temp_dir.join("Makefile"),
+            ".PHONY: lint\nCC := gcc\nlint: build\n\tcargo clippy\n%.o: %.c\n\tgcc -c $<\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.join("compose.yml"), "services: {}\n").unwrap();
+
+        let detection = detect_project(&temp_dir);
+        assert_eq!(
+            detection.files,
+            vec!["Cargo.toml", "package.json", "Makefile", "compose.yml"]
+        );
+
+        let names: Vec<&str> = detection.commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["build", "test", "clippy", "fmt", "dev", "test:e2e", "lint", "up", "down"]
+        );
+        assert_eq!(detection.commands[0].cmd, "cargo build");
+        assert_eq!(detection.commands[5].cmd, "yarn run test:e2e");
+
+        // The rendered configuration parses back with the same commands
+        let yaml = render_config("Project commands", &detection.commands);
+        assert!(yaml.starts_with(SCHEMA_HEADER));
+        let hoi: Hoi = ConfigFormat::Yaml.parse(&yaml).unwrap();
+        assert_eq!(hoi.commands.keys().collect::<Vec<_>>(), names);
+        assert_eq!(hoi.commands["lint"].cmd, "make lint");
+    }
+
+    #[test]
+    fn test_find_template() {
+        let temp_dir = testdir!();
+        fs::write(temp_dir.join("web.toml"), "version = 1\n").unwrap();
+        fs::write(temp_dir.join("rust.yml"), "version: 1\n").unwrap();
+
+        for name in BUILTIN_TEMPLATES {
+            let template = find_template(name, None).unwrap();
+            let hoi: Hoi = ConfigFormat::Yaml.parse(&template.contents).unwrap();
+            assert!(!hoi.commands.is_empty(), "{} has no commands", name);
+        }
+
+        // User templates take precedence over built-in ones
+        let template = find_template("rust", Some(&temp_dir)).unwrap();
+        assert_eq!(template.contents, "version: 1\n");
+        assert_eq!(
+            find_template("web", Some(&temp_dir)).unwrap().format,
+            ConfigFormat::Toml
+        );
+
+        let error = find_template("missing", Some(&temp_dir)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Template not found: missing. Available templates: default, docker, rust, web"
+        );
+    }
+
+    #[test]
+    fn test_import_commands() {
+        let temp_dir = testdir!();
+
+        let makefile = temp_dir.join("Makefile");
+        fs::write(
+            &makefile,
+            "## Builds the binary\nbuild:\n\tcargo build\n\nlint: build ## Runs the linters\n\tcargo clippy\nclean:\n\trm -rf target\n",
+        )
+        .unwrap();
+        let commands = import_commands(&makefile).unwrap();
+        assert_eq!(
+            commands[0],
+            ScaffoldCommand::new(
+                "build",
+                &format!("make -f {} build", makefile.display()),
+                "Builds the binary"
+            )
+        );
+        assert_eq!(commands[1].description, "Runs the linters");
+        assert_eq!(
+            commands[2].description,
+            "Runs the clean target from the Makefile."
+        );
+
+        let justfile = temp_dir.join("justfile");
+        fs::write(
+            &justfile,
+            "set shell := [\"bash\", \"-c\"]\nversion := \"1.0\"\n\n# Serves the docs\n@serve port=\"8000\":\n    mkdocs serve -a localhost:{{port}}\n\n_helper:\n    echo hidden\n\n[private]\nsecret:\n    echo hidden\n\ntest: _helper\n    cargo test\n",
+        )
+        .unwrap();
+        let commands = import_commands(&justfile).unwrap();
+        let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["serve", "test"]);
+        assert_eq!(commands[0].description, "Serves the docs");
+        assert!(commands[0].cmd.starts_with("just --justfile "));
+
+        let procfile = temp_dir.join("Procfile");
+        fs::write(
+            &procfile,
+            "# processes\nweb: bundle exec puma -p $PORT\nworker: bundle exec sidekiq\n",
+        )
+        .unwrap();
+        let commands = import_commands(&procfile).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].cmd, "bundle exec puma -p $PORT");
+
+        assert!(matches!(
+            import_commands(&temp_dir.join("build.gradle")),
+            Err(HoiError::UnsupportedImport(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_commands() {
+        let commands = vec![ScaffoldCommand::new(
+            "up",
+            "docker compose up -d",
+            "Starts it.",
+        )];
+
+        let yaml = "version: 1\ncommands:\n    build:\n        cmd: cargo build\n\n# Trailing comment\nvars:\n    name: demo\n";
+        let updated = add_commands(Path::new(".hoi.yml"), yaml, &commands).unwrap();
+        assert_eq!(
+            updated,
+            "version: 1\ncommands:\n    build:\n        cmd: cargo build\n    up:\n        cmd: \"docker compose up -d\"\n        description: \"Starts it.\"\n\n# Trailing comment\nvars:\n    name: demo\n"
+        );
+        let hoi: Hoi = ConfigFormat::Yaml.parse(&updated).unwrap();
+        assert_eq!(hoi.commands.keys().collect::<Vec<_>>(), vec!["build", "up"]);
+
+        let updated = add_commands(Path::new(".hoi.yml"), "version: 1\n", &commands).unwrap();
+        let hoi: Hoi = ConfigFormat::Yaml.parse(&updated).unwrap();
+        assert_eq!(hoi.commands["up"].cmd, "docker compose up -d");
+
+        let toml = "# Project commands\nversion = 1\n\n[commands.build]\ncmd = \"cargo build\"\n";
+        let updated = add_commands(Path::new(".hoi.toml"), toml, &commands).unwrap();
+        assert!(updated.starts_with(toml));
+        let hoi: Hoi = ConfigFormat::Toml.parse(&updated).unwrap();
+        assert_eq!(hoi.commands.keys().collect::<Vec<_>>(), vec!["build", "up"]);
+
+        let json = r#"{"version": 1, "commands": {"build": {"cmd": "cargo build"}}}"#;
+        let updated = add_commands(Path::new(".hoi.json"), json, &commands).unwrap();
+        let hoi: Hoi = ConfigFormat::Json.parse(&updated).unwrap();
+        assert_eq!(hoi.commands.keys().collect::<Vec<_>>(), vec!["build", "up"]);
+
+        assert!(add_commands(Path::new(".hoi.yml"), "commands: {}\n", &commands).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::hoi::Hoi;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+/// Generates the JSON Schema describing the .hoi.yml configuration format.
+///
+/// The schema is derived from the `Hoi` and `UserCommand` types, so it always
+/// matches what Hoi accepts.
+pub fn config_schema() -> RootSchema {
+    schema_for!(Hoi)
+}
+
+/// Renders the JSON Schema for the .hoi.yml configuration format as pretty-printed JSON.
+pub fn config_schema_json() -> String {
+    serde_json::to_string_pretty(&config_schema()).expect("JSON Schema serializes to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::Path;
+
+    // The default entrypoint differs on Windows, so the published schema is generated elsewhere.
+    #[cfg(not(windows))]
+    #[test]
+    fn test_published_schema_is_up_to_date() {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let published = std::fs::read_to_string(
+            Path::new(&manifest_dir)
+                .join("schema")
+                .join("hoi.schema.json"),
+        )
+        .expect("Failed to read published schema");
+
+        assert_eq!(
+            published.trim_end(),
+            config_schema_json(),
+            "schema/hoi.schema.json is out of date, regenerate it with `hoi schema`"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use crate::user_command::CommandArg;
+use indexmap::IndexMap;
+
+/// Replaces `{{name}}` placeholders in a command with the values of variables.
+///
+/// Whitespace inside the braces is ignored, so `{{ name }}` works too. Placeholders
+/// that don't match a variable are left untouched.
+///
+/// # Arguments
+/// * `cmd` - The command to render
+/// * `vars` - The variables available to the command
+pub fn render(cmd: &str, vars: &IndexMap<String, String>) -> String {
+    render_with(cmd, |name| vars.get(name).cloned())
+}
+
+/// How argument placeholders in a command are filled in.
+#[derive(Debug, Clone, Copy)]
+pub enum Args<'a> {
+    /// With the shell-quoted values of the arguments given on the command line.
+    Values(&'a [String]),
+    /// With references to the positional parameters of the shell, such as `"$1"`, for
+    /// scripts that receive the arguments themselves.
+    Positional,
+}
+
+/// Replaces the placeholders in a command with the arguments it was run with and the
+/// values of variables.
+///
+/// The argument placeholders are:
+/// * `{{args}}` - All arguments
+/// * `{{1}}`, `{{2}}`, ... - A single argument, counting from one
+/// * `{{args[1:]}}` - A slice of the arguments, counting from zero like `args[start:end]` in
+///   Python, so `{{args[1:]}}` is every argument after the first
+/// * `{{<name>}}` - The argument declared with this name in `args`, or its default
+///
+/// Argument values are shell-quoted; add `| raw` inside the braces, as in `{{args | raw}}`,
+/// to insert them as they are. Declared argument names take precedence over variables, and
+/// other placeholders are left untouched.
+///
+/// # Arguments
+/// * `cmd` - The command to render
+/// * `vars` - The variables available to the command
+/// * `declared` - The arguments the command declares
+/// * `args` - How to fill in the argument placeholders
+pub fn render_command(
+    cmd: &str,
+    vars: &IndexMap<String, String>,
+    declared: &[CommandArg],
+    args: Args,
+) -> String {
+    render_with(cmd, |placeholder| {
+        let (name, raw) = match placeholder.split_once('|') {
+            Some((name, filter)) if filter.trim() == "raw" => (name.trim(), true),
+            Some(_) => return None,
+            None => (placeholder, false),
+        };
+
+        match argument(name, declared) {
+            Some(argument) => Some(fill_argument(&argument, declared, args, raw)),
+            None if raw => None,
+            None => vars.get(name).cloned(),
+        }
+    })
+}
+
+/// Returns true if a command has argument placeholders, and so takes its arguments from
+/// them rather than having them passed along.
+///
+/// # Arguments
+/// * `cmd` - The command to check
+/// * `declared` - The arguments the command declares
+pub fn uses_args(cmd: &str, declared: &[CommandArg]) -> bool {
+    let mut found = false;
+    render_with(cmd, |placeholder| {
+        let name = placeholder.split('|').next().unwrap_or_default().trim();
+        found |= argument(name, declared).is_some();
+        None
+    });
+    found
+}
+
+/// Quotes a value for the shell. Values with special characters are put in single quotes, or
+/// in ANSI-C quotes (`$'...'`) when they contain line breaks or tabs, which keeps multi-line
+/// values on a single line.
+///
+/// # Arguments
+/// * `value` - The value to quote
+pub fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '-' | '_' | '.' | '/' | ':' | '=' | '+' | ',' | '@' | '%')
+        });
+    if plain {
+        return value.to_string();
+    }
+
+    if !value.contains(['\n', '\r', '\t']) {
+        return format!("'{}'", value.replace('\'', "'\\''"));
+    }
+
+    let mut quoted = String::from("$'");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Shell-quotes words with [`shell_quote`] and joins them with spaces.
+///
+/// # Arguments
+/// * `words` - The words to quote
+pub fn quote_words(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|word| shell_quote(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The arguments an argument placeholder refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Argument {
+    /// The arguments from `start` up to `end`, counting from zero, or all remaining ones.
+    Slice { start: usize, end: Option<usize> },
+    /// A single argument, counting from zero.
+    Single(usize),
+}
+
+/// Parses the name of an argument placeholder.
+fn argument(name: &str, declared: &[CommandArg]) -> Option<Argument> {
+    if name == "args" {
+        return Some(Argument::Slice {
+            start: 0,
+            end: None,
+        });
+    }
+
+    if let Some(range) = name
+        .strip_prefix("args[")
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        let (start, end) = range.split_once(':')?;
+        let start = match start.trim() {
+            "" => 0,
+            start => start.parse().ok()?,
+        };
+        let end = match end.trim() {
+            "" => None,
+            end => Some(end.parse().ok()?),
+        };
+        return Some(Argument::Slice { start, end });
+    }
+
+    if let Ok(position) = name.parse::<usize>() {
+        return position.checked_sub(1).map(Argument::Single);
+    }
+
+    declared
+        .iter()
+        .position(|arg| arg.name == name)
+        .map(Argument::Single)
+}
+
+/// Returns what an argument placeholder is replaced with.
+fn fill_argument(argument: &Argument, declared: &[CommandArg], args: Args, raw: bool) -> String {
+    let default = |index: usize| declared.get(index).and_then(|arg| arg.default.as_deref());
+
+    match (args, *argument) {
+        (Args::Values(values), Argument::Single(index)) => {
+            let value = values
+                .get(index)
+                .map(String::as_str)
+                .or_else(|| default(index));
+            match value {
+                Some(value) if raw => value.to_string(),
+                Some(value) => shell_quote(value),
+                None => String::new(),
+            }
+        }
+        (Args::Values(values), Argument::Slice { start, end }) => {
+            let end = end.unwrap_or(values.len()).min(values.len());
+            values
+                .get(start.min(end)..end)
+                .unwrap_or_default()
+                .iter()
+                .map(|value| {
+                    if raw {
+                        value.clone()
+                    } else {
+                        shell_quote(value)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        (Args::Positional, Argument::Single(index)) => {
+            let reference = match default(index) {
+                Some(default) => {
+                    let default = default.chars().fold(String::new(), |mut escaped, c| {
+                        if matches!(c, '"' | '$' | '`' | '\\' | '}') {
+                            escaped.push('\\');
+                        }
+                        escaped.push(c);
+                        escaped
+                    });
+                    format!("${{{}:-{}}}", index + 1, default)
+                }
+                None => format!("${{{}}}", index + 1),
+            };
+            if raw {
+                reference
+            } else {
+                format!("\"{}\"", reference)
+            }
+        }
+        (Args::Positional, Argument::Slice { start, end }) => {
+            let reference = match end {
+                Some(end) => format!("${{@:{}:{}}}", start + 1, end.saturating_sub(start)),
+                None if start == 0 => "$@".to_string(),
+                None => format!("${{@:{}}}", start + 1),
+            };
+            if raw {
+                reference.replace('@', "*")
+            } else {
+                format!("\"{}\"", reference)
+            }
+        }
+    }
+}
+
+/// Replaces each `{{...}}` placeholder in a command with the value returned for its trimmed
+/// contents, leaving it untouched if there is none.
+fn render_with(cmd: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut rendered = String::with_capacity(cmd.len());
+    let mut rest = cmd;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+
+        let placeholder = &rest[start..start + length + 2];
+        rendered.push_str(&rest[..start]);
+
+        match value(placeholder[2..placeholder.len() - 2].trim()) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(placeholder),
+        }
+
+        rest = &rest[start + length + 2..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let vars = IndexMap::from([
+            ("region".to_string(), "eu-west-1".to_string()),
+            ("stage".to_string(), "prod".to_string()),
+        ]);
+
+        assert_eq!(
+            render("deploy --region {{region}} --stage {{ stage }}", &vars),
+            "deploy --region eu-west-1 --stage prod"
+        );
+        assert_eq!(render("echo {{unknown}} {{", &vars), "echo {{unknown}} {{");
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("user@host:8080/path"), "user@host:8080/path");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's here"), "'it'\\''s here'");
+        assert_eq!(shell_quote("one\n'two'"), "$'one\\n\\'two\\''");
+        assert_eq!(
+            quote_words(&["echo".to_string(), "two words".to_string()]),
+            "echo 'two words'"
+        );
+    }
+
+    #[test]
+    fn test_render_command_with_args() {
+        let vars = IndexMap::from([("target".to_string(), "from vars".to_string())]);
+        let declared = vec![
+            CommandArg {
+                name: "target".to_string(),
+                ..CommandArg::default()
+            },
+            CommandArg {
+                name: "version".to_string(),
+                default: Some("latest".to_string()),
+                ..CommandArg::default()
+            },
+        ];
+        let args = vec!["staging".to_string(), "1.2".to_string(), "it's".to_string()];
+
+        let render = |cmd: &str, args: Args| render_command(cmd, &vars, &declared, args);
+        assert_eq!(
+            render(
+                "deploy {{target}} {{ version }} {{args[2:]}}",
+                Args::Values(&args)
+            ),
+            "deploy staging 1.2 'it'\\''s'"
+        );
+        assert_eq!(
+            render(
+                "echo {{args}} | {{1}} {{args[:1] | raw}}",
+                Args::Values(&args)
+            ),
+            "echo staging 1.2 'it'\\''s' | staging staging"
+        );
+        assert_eq!(
+            render(
+                "deploy {{target}} {{version}} {{4}}",
+                Args::Values(&args[..1])
+            ),
+            "deploy staging latest "
+        );
+        assert_eq!(
+            render(
+                "deploy {{target}} {{version}} {{args[1:]}}",
+                Args::Positional
+            ),
+            "deploy \"${1}\" \"${2:-latest}\" \"${@:2}\""
+        );
+
+        assert!(uses_args("echo {{ 1 }}", &[]));
+        assert!(uses_args("echo {{target | raw}}", &declared));
+        assert!(!uses_args("echo {{target}} {{0}} $1", &[]));
+    }
+}
@@ -1,17 +1,425 @@
+use crate::condition::Condition;
+use crate::environment::RequiredVar;
+use crate::template;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+/// A command that can be run with `hoi <name>`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct UserCommand {
-    pub(crate) cmd: String,
+    /// The command to run. Use the pipe operator (|) for multi-line commands, or a list of
+    /// steps to run one after another.
+    pub cmd: Cmd,
 
+    /// A short alternative name the command can be run with.
     #[serde(default, deserialize_with = "trimmed")]
-    pub(crate) alias: Option<String>,
+    pub alias: Option<String>,
 
+    /// A description of the command, shown in the command listing.
     #[serde(default)]
-    pub(crate) description: String,
+    pub description: String,
+
+    /// The group the command is listed under.
+    #[serde(default, deserialize_with = "trimmed")]
+    pub group: Option<String>,
+
+    /// A longer description of the command, shown by `hoi help <command>`.
+    #[serde(default)]
+    pub help: String,
+
+    /// Example invocations, shown by `hoi help <command>`.
+    #[serde(default)]
+    pub examples: Vec<String>,
+
+    /// The arguments the command accepts, in order.
+    #[serde(default)]
+    pub args: Vec<CommandArg>,
+
+    /// Arguments used when the command is run without any, such as `["--workspace"]`.
+    #[serde(default)]
+    pub default_args: Vec<String>,
+
+    /// Arguments always inserted before the arguments given on the command line.
+    #[serde(default)]
+    pub prepend_args: Vec<String>,
+
+    /// How the arguments given on the command line are passed to the command: "append" adds
+    /// them to the end of `cmd`, "positional" passes them as $1, $2, ... and "none" doesn't
+    /// pass them. Defaults to "none" when `cmd` has argument placeholders such as `{{args}}`,
+    /// to "append" when the command has `prepend_args` or `default_args`, and to
+    /// "positional" otherwise.
+    #[serde(default)]
+    pub pass_args: Option<PassArgs>,
+
+    /// Environment variables set for this command only.
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+
+    /// Tools this command needs, optionally with a version constraint, such as
+    /// `["docker", "node >= 18"]`. They are checked before the command runs.
+    #[serde(default)]
+    pub requires: Vec<String>,
+
+    /// Environment variables this command needs, as names or as maps of names to the
+    /// regular expressions their values must match.
+    #[serde(default)]
+    pub env_required: Vec<RequiredVar>,
+
+    /// Runs the command only when this condition holds, and skips it otherwise.
+    #[serde(default)]
+    pub r#if: Option<Condition>,
+
+    /// Skips the command when this condition holds.
+    #[serde(default)]
+    pub unless: Option<Condition>,
+
+    /// Runs the command once for every combination of these values, such as
+    /// `{toolchain: [stable, beta], features: ["", serde]}`. Each value is available as a
+    /// var and an environment variable with the name it is listed under.
+    #[serde(default, deserialize_with = "matrix")]
+    #[schemars(with = "IndexMap<String, Vec<MatrixValue>>")]
+    pub matrix: IndexMap<String, Vec<String>>,
+
+    /// Runs the combinations of the matrix at the same time instead of one after another,
+    /// starting each line of their output with the values they run with.
+    #[serde(default)]
+    pub parallel: bool,
+
+    /// Overrides the top-level entrypoint for this command only.
+    #[serde(default)]
+    pub entrypoint: Option<Vec<String>>,
+
+    /// Writes the output of every run to a timestamped log file under .hoi/logs.
+    #[serde(default)]
+    pub log: bool,
+
+    /// Keeps the command out of the command listing. It can still be run by name.
+    #[serde(default)]
+    pub hidden: bool,
+
+    /// Only lets the command run from other hoi commands, never directly from the
+    /// command line. Internal commands are hidden from the command listing too.
+    #[serde(default)]
+    pub internal: bool,
+
+    /// The profiles this command may run with. Empty means any profile, or none.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+
+    /// The configuration file this command was loaded from.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// A value of a matrix variable. Numbers and booleans are used as they are written, except
+/// that numbers lose trailing zeros, so quote versions like "3.10".
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum MatrixValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+/// Deserializes a matrix, turning its values into strings.
+fn matrix<'a, D>(deserializer: D) -> Result<IndexMap<String, Vec<String>>, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    let matrix = IndexMap::<String, Vec<MatrixValue>>::deserialize(deserializer)?;
+
+    Ok(matrix
+        .into_iter()
+        .map(|(name, values)| {
+            let values = values
+                .into_iter()
+                .map(|value| match value {
+                    MatrixValue::String(s) => s,
+                    MatrixValue::Integer(i) => i.to_string(),
+                    MatrixValue::Float(f) => f.to_string(),
+                    MatrixValue::Bool(b) => b.to_string(),
+                })
+                .collect();
+            (name, values)
+        })
+        .collect())
+}
+
+/// What a command runs: a script, or a list of steps run one after another.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Cmd {
+    /// A script run with the entrypoint of the command.
+    Script(String),
+    /// Steps run one after another, stopping at the first one that fails.
+    Steps(Vec<Step>),
+}
+
+impl Default for Cmd {
+    fn default() -> Self {
+        Cmd::Script(String::new())
+    }
+}
+
+impl From<String> for Cmd {
+    fn from(script: String) -> Self {
+        Cmd::Script(script)
+    }
+}
+
+impl From<&str> for Cmd {
+    fn from(script: &str) -> Self {
+        Cmd::Script(script.to_string())
+    }
+}
+
+impl PartialEq<&str> for Cmd {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, Cmd::Script(script) if script == other)
+    }
+}
+
+impl Cmd {
+    /// Returns the scripts the command runs: the script itself, or the `cmd` of each step.
+    pub fn scripts(&self) -> Vec<&str> {
+        match self {
+            Cmd::Script(script) => vec![script.as_str()],
+            Cmd::Steps(steps) => steps.iter().filter_map(|step| step.script()).collect(),
+        }
+    }
+}
+
+/// A step of a command.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Step {
+    /// A script run with the entrypoint of the command.
+    Script(String),
+    /// A script or another command, with its own options.
+    Detailed(Box<StepDefinition>),
+}
+
+/// A step with its own name, directory, environment variables or error handling.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StepDefinition {
+    /// The name of the step, shown in its header instead of the command it runs.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The script to run with the entrypoint of the command.
+    #[serde(default)]
+    pub cmd: Option<String>,
+
+    /// The name or alias of another command to run instead of a script.
+    #[serde(default)]
+    pub run: Option<String>,
+
+    /// The arguments to run the other command with.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// The directory to run the step in, relative to the configuration file.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+
+    /// Environment variables set for this step only.
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+
+    /// Runs the step only when this condition holds, and skips it otherwise.
+    #[serde(default)]
+    pub r#if: Option<Condition>,
+
+    /// Skips the step when this condition holds.
+    #[serde(default)]
+    pub unless: Option<Condition>,
+
+    /// Carries on with the next step when this one fails.
+    #[serde(default)]
+    pub ignore_errors: bool,
+}
+
+impl Step {
+    /// Returns the script of the step, or None if it runs another command.
+    pub fn script(&self) -> Option<&str> {
+        match self {
+            Step::Script(script) => Some(script),
+            Step::Detailed(step) => step.cmd.as_deref(),
+        }
+    }
+
+    /// Returns the options of the step. Steps written as a plain script have none.
+    pub fn definition(&self) -> StepDefinition {
+        match self {
+            Step::Script(script) => StepDefinition {
+                cmd: Some(script.clone()),
+                ..StepDefinition::default()
+            },
+            Step::Detailed(step) => step.as_ref().clone(),
+        }
+    }
+
+    /// Returns the title shown in the header of the step: its name, the other command it
+    /// runs, or the first line of its script.
+    pub fn title(&self) -> String {
+        let step = self.definition();
+        if let Some(name) = step.name {
+            return name;
+        }
+
+        match (step.run, step.cmd) {
+            (Some(run), _) => {
+                let mut words = vec!["hoi".to_string(), run];
+                words.extend(step.args);
+                template::quote_words(&words)
+            }
+            (None, Some(cmd)) => cmd.trim().lines().next().unwrap_or_default().to_string(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// An argument a command accepts.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct CommandArg {
+    /// The name of the argument.
+    pub name: String,
+
+    /// A description of the argument, shown by `hoi help <command>`.
+    #[serde(default)]
+    pub description: String,
+
+    /// Whether the argument must be given.
+    #[serde(default)]
+    pub required: bool,
+
+    /// The value used when the argument is not given.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// How the arguments given on the command line are passed to a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PassArgs {
+    /// Shell-quotes the arguments and adds them to the end of the command.
+    Append,
+    /// Passes the arguments to the entrypoint, where a shell sees them as $1, $2, ...
+    Positional,
+    /// Doesn't pass the arguments on; they are only used by placeholders.
+    None,
+}
+
+impl UserCommand {
+    /// Returns how the arguments given on the command line are passed to the command: the
+    /// `pass_args` setting, or "none" if the command has argument placeholders, "append" if
+    /// it has `prepend_args` or `default_args` to add to the end of it, and "positional"
+    /// otherwise.
+    pub fn pass_args(&self) -> PassArgs {
+        self.pass_args.unwrap_or_else(|| {
+            let uses_args = self
+                .cmd
+                .scripts()
+                .iter()
+                .any(|script| template::uses_args(script, &self.args));
+            if uses_args {
+                PassArgs::None
+            } else if !self.prepend_args.is_empty() || !self.default_args.is_empty() {
+                PassArgs::Append
+            } else {
+                PassArgs::Positional
+            }
+        })
+    }
+
+    /// Returns the arguments the command runs with: its `prepend_args`, followed by the given
+    /// arguments, or its `default_args` when none are given.
+    ///
+    /// # Arguments
+    /// * `args` - The arguments given on the command line
+    pub fn effective_args(&self, args: &[String]) -> Vec<String> {
+        let args = if args.is_empty() {
+            &self.default_args
+        } else {
+            args
+        };
+
+        self.prepend_args.iter().chain(args).cloned().collect()
+    }
+
+    /// Returns the name of the first required argument that is missing from the given
+    /// arguments.
+    ///
+    /// # Arguments
+    /// * `args` - The arguments given on the command line
+    pub fn missing_argument(&self, args: &[String]) -> Option<&str> {
+        self.args
+            .iter()
+            .enumerate()
+            .find(|(index, arg)| arg.required && *index >= args.len())
+            .map(|(_, arg)| arg.name.as_str())
+    }
+
+    /// Returns the combinations of the values of the matrix, with the first variable changing
+    /// the slowest. Commands without a matrix have none.
+    pub fn matrix_combinations(&self) -> Vec<IndexMap<String, String>> {
+        if self.matrix.is_empty() {
+            return Vec::new();
+        }
+
+        let mut combinations = vec![IndexMap::new()];
+        for (name, values) in &self.matrix {
+            combinations = combinations
+                .iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.insert(name.clone(), value.clone());
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+    }
+
+    /// Returns what is wrong with the matrix, if anything: a variable without values, or with
+    /// a name that can't be used as an environment variable in a shell.
+    pub fn matrix_problem(&self) -> Option<String> {
+        self.matrix.iter().find_map(|(name, values)| {
+            let valid_name = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+            if !valid_name {
+                Some(format!(
+                    "uses '{}' as a name, which is not a valid variable name",
+                    name
+                ))
+            } else if values.is_empty() {
+                Some(format!("has no values for '{}'", name))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns true if the command is left out of the command listing.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden || self.internal
+    }
+
+    /// Returns the directory of the configuration file this command was defined in.
+    pub fn dir(&self) -> Option<&Path> {
+        self.source.as_deref().and_then(Path::parent)
+    }
 }
 
-fn trimmed<'a, D>(deserializer: D) -> Result<Option<String>, D::Error>
+pub(crate) fn trimmed<'a, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'a>,
 {
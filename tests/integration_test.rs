@@ -152,6 +152,682 @@ fn test_hoi_with_env_files() {
     });
 }
 
+#[test]
+fn test_hoi_toml_config() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.toml", &temp_dir, ".hoi.toml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+
+    let output = run_hoi_command(&binary_path, &[], &temp_dir);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("TOML integration test config"));
+    assert!(stdout.contains("echo-toml"));
+
+    let output = run_hoi_command(&binary_path, &["et"], &temp_dir);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("TOML command successful"));
+}
+
+#[test]
+fn test_hoi_reports_broken_config() {
+    let temp_dir: PathBuf = testdir!();
+    fs::write(
+        temp_dir.join(".hoi.yml"),
+        "commands:\n  build:\n    cmd: echo\n    description: [echo\n",
+    )
+    .unwrap();
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &[], &temp_dir);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to parse"), "{}", stderr);
+    assert!(stderr.contains(".hoi.yml:"), "{}", stderr);
+    assert!(stderr.contains("description: [echo"), "{}", stderr);
+    assert!(stderr.contains('^'), "{}", stderr);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_hoi_masks_secrets() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.secrets.yml", &temp_dir, ".hoi.yml");
+    copy_fixture(".env.local", &temp_dir, ".env.local");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["echo-secrets"], &temp_dir);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("token=*** local=***"), "{}", stdout);
+    assert!(stderr.contains("public=visible"), "{}", stderr);
+    assert!(!stdout.contains("hunter2"));
+    assert!(!stdout.contains("local_value"));
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_hoi_writes_logs() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.logs.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    for _ in 0..3 {
+        let output = run_hoi_command(&binary_path, &["build"], &temp_dir);
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("building"));
+    }
+
+    // Only the two most recent logs are kept
+    let log_dir = temp_dir.join(".hoi").join("logs").join("build");
+    assert_eq!(fs::read_dir(&log_dir).unwrap().count(), 2);
+
+    let output = run_hoi_command(&binary_path, &["logs", "b"], &temp_dir);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("] $ hoi build"), "{}", stdout);
+    assert!(stdout.contains("] building"), "{}", stdout);
+    assert!(stdout.contains("] warning: something"), "{}", stdout);
+    assert!(stdout.contains("] Exited with code: Some(0)"), "{}", stdout);
+
+    let output = run_hoi_command(&binary_path, &["logs", "missing"], &temp_dir);
+    assert!(!output.status.success());
+
+    // A command named logs replaces the built-in one
+    let mut config = fs::read_to_string(temp_dir.join(".hoi.yml")).unwrap();
+    config.push_str("  logs:\n    cmd: echo \"service logs\"\n");
+    fs::write(temp_dir.join(".hoi.yml"), config).unwrap();
+
+    let output = run_hoi_command(&binary_path, &["logs"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("service logs"), "{}", stdout);
+
+    let output = run_hoi_command(&binary_path, &[], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches(" logs ").count(), 1, "{}", stdout);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_hoi_runs_parent_commands_in_their_directory() {
+    let temp_dir: PathBuf = testdir!();
+    let service_dir = temp_dir.join("services").join("api");
+    fs::create_dir_all(temp_dir.join("scripts")).unwrap();
+    fs::create_dir_all(service_dir.join("src")).unwrap();
+
+    fs::write(
+        temp_dir.join(".hoi.yml"),
+        "version: 1\nroot: true\ncommands:\n  lint:\n    cmd: sh ./scripts/lint.sh\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.join("scripts").join("lint.sh"),
+        "echo \"linting in $(basename \"$PWD\")\"\n",
+    )
+    .unwrap();
+    fs::write(
+        service_dir.join(".hoi.yml"),
+        "version: 1\ncommands:\n  where:\n    cmd: basename \"$PWD\"\n",
+    )
+    .unwrap();
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    // Commands run in the directory of the file that defines them, from any subdirectory
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["lint"], &service_dir.join("src"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let root_name = temp_dir.file_name().unwrap().to_string_lossy();
+    assert!(
+        stdout.contains(&format!("linting in {}\n", root_name)),
+        "{}",
+        stdout
+    );
+
+    let output = run_hoi_command(&binary_path, &["where"], &service_dir.join("src"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("api\n"), "{}", stdout);
+}
+
+#[test]
+fn test_hoi_history_and_last() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["last"], &temp_dir);
+    assert!(!output.status.success());
+
+    let output = run_hoi_command(&binary_path, &["echo-test", "--verbose"], &temp_dir);
+    assert!(output.status.success());
+
+    // The previous command runs again with the same arguments
+    let output = run_hoi_command(&binary_path, &["last"], &temp_dir);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Running command echo-test..."),
+        "{}",
+        stdout
+    );
+
+    let output = run_hoi_command(&binary_path, &["history", "echo-test"], &temp_dir);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.matches("echo-test --verbose").count(),
+        2,
+        "{}",
+        stdout
+    );
+
+    let output = run_hoi_command(&binary_path, &["history", "--failed"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("No matching runs found"), "{}", stdout);
+}
+
+#[test]
+fn test_hoi_history_records_every_run() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.history.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["greet", "b c", "hunter2"], &temp_dir);
+    assert!(output.status.success());
+
+    // Masked secrets can't be run again
+    let output = run_hoi_command(&binary_path, &["last"], &temp_dir);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Run `hoi greet` again"), "{}", stderr);
+
+    // Runs that fail before they start are recorded too
+    let output = run_hoi_command(&binary_path, &["needs-tool"], &temp_dir);
+    assert!(!output.status.success());
+
+    // Secret values never reach the history file
+    let recorded = fs::read_to_string(temp_dir.join(".hoi").join("history.jsonl")).unwrap();
+    assert!(!recorded.contains("hunter2"), "{}", recorded);
+
+    let output = run_hoi_command(&binary_path, &["history"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("greet --loud 'b c' '***'"), "{}", stdout);
+
+    let output = run_hoi_command(&binary_path, &["history", "--failed"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("needs-tool"), "{}", stdout);
+    assert!(stdout.contains("error"), "{}", stdout);
+    assert!(!stdout.contains("greet"), "{}", stdout);
+
+    // Commands named history and last replace the built-in ones
+    let mut config = fs::read_to_string(temp_dir.join(".hoi.yml")).unwrap();
+    config.push_str("  history:\n    cmd: echo \"my history\"\n");
+    config.push_str("  last:\n    cmd: echo \"my last\"\n");
+    fs::write(temp_dir.join(".hoi.yml"), config).unwrap();
+
+    let output = run_hoi_command(&binary_path, &["history"], &temp_dir);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("my history"));
+    let output = run_hoi_command(&binary_path, &["last"], &temp_dir);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("my last"));
+}
+
+#[test]
+fn test_hoi_unknown_profile_from_environment() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.profiles.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    // A profile from HOI_PROFILE that this configuration doesn't define is only a warning
+    let binary_path = get_binary_path();
+    let mut cmd = Command::new(&binary_path);
+    cmd.current_dir(&temp_dir).env("HOI_PROFILE", "elsewhere");
+    #[cfg(not(windows))]
+    cmd.env("HOME", &temp_dir);
+    #[cfg(windows)]
+    cmd.env("USERPROFILE", &temp_dir);
+    let output = cmd.output().expect("Failed to execute hoi binary");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: Profile not found: elsewhere"),
+        "{}",
+        stderr
+    );
+
+    // A profile given with --profile has to exist
+    let output = run_hoi_command(&binary_path, &["--profile", "elsewhere"], &temp_dir);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_hoi_user_commands_replace_builtins() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.builtins.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    for name in ["export", "import", "schema"] {
+        let output = run_hoi_command(&binary_path, &[name], &temp_dir);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains(&format!("my {}", name)), "{}", stdout);
+    }
+
+    // The listing only shows the user command
+    let output = run_hoi_command(&binary_path, &[], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches(" export ").count(), 1, "{}", stdout);
+    assert_eq!(stdout.matches(" import ").count(), 1, "{}", stdout);
+    assert_eq!(stdout.matches(" schema ").count(), 1, "{}", stdout);
+    assert!(!stdout.contains("detailed help of a command"), "{}", stdout);
+
+    // A help command replaces `hoi help` with or without a command name, while
+    // `hoi <command> --help` still shows the built-in help
+    for args in [&["help"][..], &["help", "export"]] {
+        let output = run_hoi_command(&binary_path, args, &temp_dir);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("my help"), "{}", stdout);
+    }
+    let output = run_hoi_command(&binary_path, &["export", "--help"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Exports the database"), "{}", stdout);
+    assert!(!stdout.contains("Running command"), "{}", stdout);
+}
+
+#[test]
+fn test_hoi_init_detects_project() {
+    let temp_dir: PathBuf = testdir!();
+    fs::write(temp_dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["init"], &temp_dir);
+    assert!(output.status.success());
+    let config = fs::read_to_string(temp_dir.join(".hoi.yml")).unwrap();
+    assert!(config.contains("cmd: \"cargo clippy"), "{}", config);
+
+    // Templates from ~/.hoi/templates can scaffold the global configuration
+    let templates_dir = temp_dir.join(".hoi").join("templates");
+    fs::create_dir_all(&templates_dir).unwrap();
+    fs::write(
+        templates_dir.join("mine.toml"),
+        "version = 1\n[commands.hi]\ncmd = \"echo hi\"\n",
+    )
+    .unwrap();
+
+    let output = run_hoi_command(
+        &binary_path,
+        &["init", "--global", "--template", "mine"],
+        &temp_dir,
+    );
+    assert!(output.status.success());
+    assert!(temp_dir.join(".hoi").join(".hoi.global.toml").exists());
+
+    let empty_dir = temp_dir.join("empty");
+    fs::create_dir_all(&empty_dir).unwrap();
+    let output = run_hoi_command(&binary_path, &["init", "--template", "missing"], &empty_dir);
+    assert!(!output.status.success());
+    assert!(!empty_dir.join(".hoi.yml").exists());
+}
+
+#[test]
+fn test_hoi_import_keeps_existing_commands() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.yml", &temp_dir, ".hoi.yml");
+    fs::write(
+        temp_dir.join("package.json"),
+        r#"{"scripts": {"echo-test": "echo replaced", "start": "node server.js"}}"#,
+    )
+    .unwrap();
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["import", "package.json"], &temp_dir);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Imported 1 commands"), "{}", stdout);
+    assert!(stdout.contains("already exist: echo-test"), "{}", stdout);
+
+    let config = fs::read_to_string(temp_dir.join(".hoi.yml")).unwrap();
+    assert!(config.contains("Integration test successful"), "{}", config);
+    assert!(config.contains("cmd: \"npm run start\""), "{}", config);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_hoi_hidden_and_internal_commands() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.hidden.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &[], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("build"), "{}", stdout);
+    assert!(!stdout.contains("debug-info"), "{}", stdout);
+    assert!(!stdout.contains("_ensure-tools"), "{}", stdout);
+
+    // Hidden commands still run, internal ones only from other commands
+    let output = run_hoi_command(&binary_path, &["debug-info"], &temp_dir);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("debug info"));
+
+    let output = run_hoi_command(&binary_path, &["_ensure-tools"], &temp_dir);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is internal"));
+
+    let output = Command::new(&binary_path)
+        .arg("build")
+        .current_dir(&temp_dir)
+        .env("HOME", &temp_dir)
+        .env("HOI_TEST_BIN", &binary_path)
+        .output()
+        .expect("Failed to execute hoi binary");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("tools are installed\n"), "{}", stdout);
+    assert!(stdout.contains("building"), "{}", stdout);
+
+    // Steps run internal commands without starting hoi again
+    let output = run_hoi_command(&binary_path, &["test"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("tools are installed\n"), "{}", stdout);
+    assert!(stdout.contains("testing"), "{}", stdout);
+}
+
+#[test]
+fn test_hoi_listing_banner_tips_and_color() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.listing.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &[], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Welcome to the listing test"), "{}", stdout);
+    assert!(!stdout.contains("Hoi Hoi!"), "{}", stdout);
+    assert!(
+        stdout.contains("Did you know? Run hoi deploy on Fridays at your own risk."),
+        "{}",
+        stdout
+    );
+    // Output to a pipe isn't colored by default
+    assert!(!stdout.contains('\x1b'), "{}", stdout);
+
+    let output = run_hoi_command(
+        &binary_path,
+        &["--no-banner", "--color", "always"],
+        &temp_dir,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Welcome"), "{}", stdout);
+    assert!(!stdout.contains("Did you know?"), "{}", stdout);
+    assert!(stdout.contains("\x1b[1;36mdeploy\x1b[0m"), "{}", stdout);
+    assert!(stdout.contains("\x1b[33md\x1b[0m"), "{}", stdout);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_hoi_runs_steps() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.steps.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["check"], &temp_dir);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("==> [1/3] echo \"formatting\"\nformatting\n==> [2/3] Lint\nlinting\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("never printed"), "{}", stdout);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Step 2/3 (Lint) of command 'check' failed with exit code: Some(2)"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Command 'check' failed with exit code: Some(2)"),
+        "{}",
+        stderr
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hoi_checks_requirements() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.requires.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["deploy"], &temp_dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Command 'deploy' needs tools that are missing or out of date:"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("  - hoi-missing-tool >= 2: not found on PATH"),
+        "{}",
+        stderr
+    );
+    assert!(!temp_dir.join("deployed.txt").exists());
+
+    let output = run_hoi_command(&binary_path, &["greet"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("hello"), "{}", stdout);
+}
+
+#[test]
+fn test_hoi_checks_required_environment() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.env_required.yml", &temp_dir, ".hoi.yml");
+    fs::write(
+        temp_dir.join(".env"),
+        "DATABASE_URL=mysql://localhost/app\n",
+    )
+    .unwrap();
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["migrate"], &temp_dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "Command 'migrate' needs environment variables that are missing or invalid:\n  \
+             - HOI_INTEGRATION_PROFILE: not set\n  \
+             - DATABASE_URL: does not match ^postgres://\n"
+        ),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains(".env.local, then run"), "{}", stderr);
+    assert!(!temp_dir.join("migrated.txt").exists());
+
+    // Each command is checked right before it starts, so earlier steps still run
+    let output = run_hoi_command(&binary_path, &["release"], &temp_dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Command 'migrate' needs environment variables"),
+        "{}",
+        stderr
+    );
+    assert!(temp_dir.join("built.txt").exists());
+    assert!(!temp_dir.join("migrated.txt").exists());
+
+    fs::write(
+        temp_dir.join(".env.local"),
+        "DATABASE_URL=postgres://localhost/app\nHOI_INTEGRATION_PROFILE=dev\n",
+    )
+    .unwrap();
+    run_hoi_command(&binary_path, &["migrate"], &temp_dir);
+    assert!(temp_dir.join("migrated.txt").exists());
+}
+
+#[test]
+fn test_hoi_skips_commands_and_steps() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.conditions.yml", &temp_dir, ".hoi.yml");
+    fs::write(temp_dir.join("installed.txt"), "").unwrap();
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["publish"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Skipped command 'publish' (if: env HOI_INTEGRATION_CI)"),
+        "{}",
+        stdout
+    );
+    assert!(!temp_dir.join("published.txt").exists());
+
+    let output = run_hoi_command(&binary_path, &["setup"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "==> [1/2] echo \"installing\" (skipped, unless: file installed.txt)\n\
+             ==> [2/2] echo \"ready\"\nready\n"
+        ),
+        "{}",
+        stdout
+    );
+
+    let output = run_hoi_command(&binary_path, &["history"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("skipped"), "{}", stdout);
+
+    let output = run_hoi_command(&binary_path, &["history", "--failed"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("No matching runs found"), "{}", stdout);
+}
+
+#[test]
+fn test_hoi_runs_matrix() {
+    let temp_dir: PathBuf = testdir!();
+    copy_fixture(".hoi.matrix.yml", &temp_dir, ".hoi.yml");
+
+    Command::new("cargo")
+        .args(["build"])
+        .status()
+        .expect("Failed to build hoi binary");
+
+    let binary_path = get_binary_path();
+    let output = run_hoi_command(&binary_path, &["test"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "==> [matrix 1/4] toolchain=stable, features=\"\"\n\
+             testing on stable with []\n\
+             ==> [matrix 2/4] toolchain=stable, features=serde\n\
+             testing on stable with [serde]\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Results of test:"), "{}", stdout);
+    assert_eq!(stdout.matches(" ok ").count(), 4, "{}", stdout);
+
+    let output = run_hoi_command(&binary_path, &["versions"], &temp_dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("[python=3.10] ") && stdout.contains("[python=3.12] "),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        fs::read_to_string(temp_dir.join("python-3.10.txt")).unwrap(),
+        "python 3.10\n"
+    );
+    assert_eq!(
+        fs::read_to_string(temp_dir.join("python-3.12.txt")).unwrap(),
+        "python 3.12\n"
+    );
+}
+
 /// Helper to spawn the hoi binary with proper mocked HOME
 fn run_hoi_command(binary: &Path, args: &[&str], cwd: &Path) -> Output {
     let mut cmd = Command::new(binary);